@@ -1,50 +1,149 @@
-const NAUGHTY_PARTS: [&str; 4] = ["ab", "cd", "pq", "xy"];
-const VOWELS: [char; 5] = ['a', 'e', 'i', 'o', 'u'];
+use std::{collections::HashMap, fmt, fs, str::FromStr};
 
-fn has_naughty_parts(s: &str) -> bool {
-    NAUGHTY_PARTS.iter().any(|&part| s.contains(part))
-}
+use anyhow::{bail, Context};
+
+const DEFAULT_RULES: &str = include_str!("../../rules/05.txt");
 
-fn has_duplicated_letter(s: &str) -> bool {
-    s.as_bytes().windows(2).any(|pair| pair[0] == pair[1])
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    Forbidden(Vec<String>),
+    MinCount { count: usize, set: Vec<char> },
+    RepeatedLetter { gap: usize },
+    RepeatedPair,
 }
 
-fn has_3_vowels(s: &str) -> bool {
-    s.chars().filter(|&c| VOWELS.contains(&c)).count() >= 3
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split_whitespace().collect::<Vec<_>>();
+        match parts.as_slice() {
+            ["forbid", substrings @ ..] if !substrings.is_empty() => Ok(Rule::Forbidden(
+                substrings.iter().map(|&p| p.to_owned()).collect(),
+            )),
+            ["count", count, set] => Ok(Rule::MinCount {
+                count: count.parse().context("Invalid count")?,
+                set: set.chars().collect(),
+            }),
+            ["repeat", gap] => Ok(Rule::RepeatedLetter {
+                gap: gap.parse().context("Invalid gap")?,
+            }),
+            ["pair"] => Ok(Rule::RepeatedPair),
+            _ => bail!("Unknown rule '{}'", s),
+        }
+    }
 }
 
-fn is_nice_part1(s: &str) -> bool {
-    !has_naughty_parts(s) && has_duplicated_letter(s) && has_3_vowels(s)
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Forbidden(substrings) => write!(f, "forbid {}", substrings.join(" ")),
+            Rule::MinCount { count, set } => {
+                write!(f, "count {} {}", count, set.iter().collect::<String>())
+            }
+            Rule::RepeatedLetter { gap } => write!(f, "repeat {}", gap),
+            Rule::RepeatedPair => write!(f, "pair"),
+        }
+    }
 }
 
-fn has_sandwiched_letter(s: &str) -> bool {
-    s.as_bytes().windows(3).any(|pair| pair[0] == pair[2])
+fn has_repeated_pair(s: &[u8]) -> bool {
+    let mut first_seen = HashMap::new();
+    s.windows(2).enumerate().any(|(i, pair)| {
+        let first = *first_seen.entry(pair).or_insert(i);
+        i >= first + 2
+    })
 }
 
-fn has_duplicated_pair(s: &str) -> bool {
-    for i in 0..(s.len() - 3) {
-        for j in (i + 2)..(s.len() - 1) {
-            if s[i..=(i + 1)] == s[j..=(j + 1)] {
-                return true;
+impl Rule {
+    fn matches(&self, s: &str) -> bool {
+        match self {
+            Rule::Forbidden(substrings) => !substrings.iter().any(|p| s.contains(p.as_str())),
+            Rule::MinCount { count, set } => {
+                s.chars().filter(|c| set.contains(c)).count() >= *count
             }
+            Rule::RepeatedLetter { gap } => {
+                s.as_bytes().windows(gap + 2).any(|w| w[0] == w[gap + 1])
+            }
+            Rule::RepeatedPair => has_repeated_pair(s.as_bytes()),
         }
     }
-    false
 }
 
-fn is_nice_part2(s: &str) -> bool {
-    has_sandwiched_letter(s) && has_duplicated_pair(s)
+#[derive(Debug, Clone)]
+struct RuleSet {
+    name: String,
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    fn is_nice(&self, s: &str) -> bool {
+        self.rules.iter().all(|r| r.matches(s))
+    }
+
+    fn explain<'a>(&'a self, s: &str) -> Vec<(&'a Rule, bool)> {
+        self.rules.iter().map(|r| (r, r.matches(s))).collect()
+    }
+}
+
+// Rule sets start with a `[name]` header followed by one rule per line, `#` starts a comment.
+fn parse_rule_sets(s: &str) -> anyhow::Result<Vec<RuleSet>> {
+    let mut result: Vec<RuleSet> = vec![];
+    for (n, line) in s.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            result.push(RuleSet {
+                name: name.trim().to_owned(),
+                rules: vec![],
+            });
+        } else {
+            let rule = Rule::from_str(line).with_context(|| format!("Line {}", n + 1))?;
+            result
+                .last_mut()
+                .with_context(|| format!("Line {}: rule outside of a rule set", n + 1))?
+                .rules
+                .push(rule);
+        }
+    }
+    Ok(result)
 }
 
 fn main() {
+    let mut explain = false;
+    let mut rules_path = None;
+    for arg in std::env::args().skip(1) {
+        if arg == "--explain" {
+            explain = true;
+        } else {
+            rules_path = Some(arg);
+        }
+    }
+    let rules = match rules_path {
+        Some(path) => fs::read_to_string(path).expect("Rules file not found"),
+        None => DEFAULT_RULES.to_owned(),
+    };
+    let rule_sets = parse_rule_sets(&rules).expect("Invalid rules");
+
     let input = aoc::get_input(15, 5);
     let strings = input.lines().collect::<Vec<_>>();
-    println!(
-        "Part 1: {}",
-        strings.iter().filter(|s| is_nice_part1(s)).count()
-    );
-    println!(
-        "Part 2: {}",
-        strings.iter().filter(|s| is_nice_part2(s)).count()
-    );
+    if explain {
+        for s in &strings {
+            println!("{}", s);
+            for set in &rule_sets {
+                for (rule, passed) in set.explain(s) {
+                    let verdict = if passed { "pass" } else { "FAIL" };
+                    println!("  [{}] {}: {}", set.name, rule, verdict);
+                }
+            }
+        }
+    }
+    for set in &rule_sets {
+        println!(
+            "{}: {}",
+            set.name,
+            strings.iter().filter(|s| set.is_nice(s)).count()
+        );
+    }
 }
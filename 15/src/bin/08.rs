@@ -1,31 +1,94 @@
-fn display_store_diff(s: &str) -> usize {
-    let mut result = 0;
-    let mut it = s.chars();
-    loop {
-        let current = it.next();
-        if current.is_none() {
-            break;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+enum DecodeErrorKind {
+    MissingQuotes,
+    UnescapedQuote,
+    UnknownEscape(char),
+    TruncatedEscape,
+    InvalidHex(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct DecodeError {
+    position: usize,
+    kind: DecodeErrorKind,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: ", self.position)?;
+        match &self.kind {
+            DecodeErrorKind::MissingQuotes => write!(f, "literal is not enclosed in quotes"),
+            DecodeErrorKind::UnescapedQuote => write!(f, "unescaped quote inside literal"),
+            DecodeErrorKind::UnknownEscape(c) => write!(f, "unknown escape sequence '\\{}'", c),
+            DecodeErrorKind::TruncatedEscape => write!(f, "escape sequence cut short"),
+            DecodeErrorKind::InvalidHex(digits) => write!(f, "invalid hex escape '\\x{}'", digits),
         }
-        let current = current.unwrap();
-        if current == '\"' {
-            result += 1;
-        } else if current == '\\' {
-            let next = it.next().unwrap();
-            if next == 'x' {
-                result += 3;
-                it.next();
-                it.next();
-            } else {
-                result += 1;
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn decode(literal: &str) -> Result<Vec<u8>, DecodeError> {
+    let error = |position, kind| DecodeError { position, kind };
+    let bytes = literal.as_bytes();
+    if bytes.len() < 2 || bytes[0] != b'"' || bytes[bytes.len() - 1] != b'"' {
+        return Err(error(0, DecodeErrorKind::MissingQuotes));
+    }
+    let end = bytes.len() - 1;
+    let mut result = vec![];
+    let mut i = 1;
+    while i < end {
+        match bytes[i] {
+            b'"' => return Err(error(i, DecodeErrorKind::UnescapedQuote)),
+            b'\\' => {
+                let escaped = *bytes
+                    .get(i + 1)
+                    .filter(|_| i + 1 < end)
+                    .ok_or_else(|| error(i, DecodeErrorKind::TruncatedEscape))?;
+                match escaped {
+                    b'\\' | b'"' => {
+                        result.push(escaped);
+                        i += 2;
+                    }
+                    b'x' => {
+                        if i + 4 > end {
+                            return Err(error(i, DecodeErrorKind::TruncatedEscape));
+                        }
+                        let digits = &bytes[i + 2..i + 4];
+                        if !digits.iter().all(u8::is_ascii_hexdigit) {
+                            let digits = String::from_utf8_lossy(digits).into_owned();
+                            return Err(error(i, DecodeErrorKind::InvalidHex(digits)));
+                        }
+                        let value = |d: u8| (d as char).to_digit(16).unwrap() as u8;
+                        result.push(value(digits[0]) << 4 | value(digits[1]));
+                        i += 4;
+                    }
+                    c => return Err(error(i, DecodeErrorKind::UnknownEscape(c as char))),
+                }
+            }
+            c => {
+                result.push(c);
+                i += 1;
             }
         }
     }
-    result
+    Ok(result)
 }
 
-fn display_escaped_diff(s: &str) -> usize {
-    s.chars()
-        .fold(2, |acc, c| acc + if c == '\\' || c == '\"' { 1 } else { 0 })
+fn encode(bytes: &[u8]) -> String {
+    let mut result = String::from("\"");
+    for &b in bytes {
+        match b {
+            b'"' => result.push_str("\\\""),
+            b'\\' => result.push_str("\\\\"),
+            0x20..=0x7e => result.push(b as char),
+            _ => result.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    result.push('"');
+    result
 }
 
 fn main() {
@@ -34,15 +97,54 @@ fn main() {
         .split('\n')
         .map(str::to_owned)
         .collect::<Vec<_>>();
+    let decoded = strings
+        .iter()
+        .map(|s| decode(s))
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Invalid input");
 
     println!(
         "Part 1: {}",
-        strings.iter().fold(0, |acc, s| acc + display_store_diff(s))
+        strings
+            .iter()
+            .zip(decoded.iter())
+            .map(|(s, d)| s.len() - d.len())
+            .sum::<usize>()
     );
     println!(
         "Part 2: {}",
         strings
             .iter()
-            .fold(0, |acc, s| acc + display_escaped_diff(s))
+            .map(|s| encode(s.as_bytes()).len() - s.len())
+            .sum::<usize>()
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut rng = aoc::XorShift::new(8);
+        let mut cases = vec![(0..=u8::MAX).collect::<Vec<_>>()];
+        for _ in 0..1000 {
+            let len = rng.below(40) as usize;
+            cases.push((0..len).map(|_| rng.below(256) as u8).collect());
+        }
+        for bytes in cases {
+            let encoded = encode(&bytes);
+            assert_eq!(decode(&encoded), Ok(bytes), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn invalid_hex() {
+        for (literal, digits) in [(r#""\x+5""#, "+5"), (r#""\xaé""#, "a\u{fffd}")] {
+            assert_eq!(
+                decode(literal).map_err(|e| e.kind),
+                Err(DecodeErrorKind::InvalidHex(digits.to_owned()))
+            );
+        }
+    }
+}
@@ -1,65 +1,18 @@
-use std::{cmp, collections::HashMap};
+use aoc::{EdgeKind, Graph, Storage};
 
-#[derive(Debug, Clone)]
-struct Grid {
-    data: Vec<i32>,
-    n_columns: usize,
-}
-
-impl Grid {
-    fn new(n_rows: usize, n_columns: usize) -> Grid {
-        Grid {
-            data: vec![0; n_rows * n_columns],
-            n_columns,
-        }
-    }
-
-    fn to_index(&self, r: usize, c: usize) -> usize {
-        r * self.n_columns + c
-    }
-
-    fn get(&self, r: usize, c: usize) -> i32 {
-        self.data[self.to_index(r, c)]
-    }
-
-    fn set(&mut self, r: usize, c: usize, v: i32) {
-        let index = self.to_index(r, c);
-        self.data[index] = v;
-    }
-
-    fn n_columns(&self) -> usize {
-        self.n_columns
-    }
-}
-
-fn to_connection_map(conns: &[(String, String, i32)]) -> Grid {
-    let mut curr = 0;
-    let mut nodes = HashMap::new();
-    for (n1, n2, _) in conns {
-        if !nodes.contains_key(&n1 as &str) {
-            nodes.insert(n1.clone(), curr);
-            curr += 1;
-        }
-        if !nodes.contains_key(&n2 as &str) {
-            nodes.insert(n2.clone(), curr);
-            curr += 1;
-        }
-    }
-    let mut grid = Grid::new(nodes.len(), nodes.len());
-    for (n1, n2, d) in conns {
-        let i1 = *nodes.get(n1).unwrap();
-        let i2 = *nodes.get(n2).unwrap();
-        grid.set(i1, i2, *d);
-        grid.set(i2, i1, *d);
-    }
-    grid
-}
-
-fn weight(graph: &Grid, permutation: &[usize]) -> i32 {
-    permutation.windows(2).map(|e| graph.get(e[0], e[1])).sum()
+#[cfg(test)]
+fn weight(graph: &Graph<i32>, permutation: &[usize]) -> Option<i32> {
+    permutation
+        .windows(2)
+        .map(|e| graph.weight(e[0], e[1]).copied())
+        .sum()
 }
 
+#[cfg(test)]
 fn next_permutation(permutation: &mut [usize]) -> bool {
+    if permutation.len() < 2 {
+        return false;
+    }
     let mut i = (permutation.len() - 2) as i64;
     while i >= 0 && permutation[i as usize + 1] <= permutation[i as usize] {
         i -= 1;
@@ -77,38 +30,158 @@ fn next_permutation(permutation: &mut [usize]) -> bool {
     }
 }
 
-fn min_max_path(graph: &Grid) -> (i32, i32) {
-    let mut permutation = (0..graph.n_columns()).collect::<Vec<_>>();
-    let mut min = weight(&graph, &permutation);
-    let mut max = weight(&graph, &permutation);
+#[cfg(test)]
+fn min_max_path(graph: &Graph<i32>) -> Option<(i32, i32)> {
+    let mut permutation = (0..graph.len()).collect::<Vec<_>>();
+    let mut result: Option<(i32, i32)> = None;
     loop {
-        if next_permutation(&mut permutation) {
-            let current = weight(graph, &permutation);
-            min = cmp::min(min, current);
-            max = cmp::max(max, current);
-        } else {
+        if let Some(current) = weight(graph, &permutation) {
+            result = Some(match result {
+                Some((min, max)) => (min.min(current), max.max(current)),
+                None => (current, current),
+            });
+        }
+        if !next_permutation(&mut permutation) {
             break;
         }
     }
-    (min, max)
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Objective {
+    Shortest,
+    Longest,
+}
+
+impl Objective {
+    fn is_better(&self, candidate: i32, current: i32) -> bool {
+        match self {
+            Objective::Shortest => candidate < current,
+            Objective::Longest => candidate > current,
+        }
+    }
+}
+
+// Held-Karp over (visited set, last node), O(n^2 * 2^n) instead of O(n!)
+fn best_path(graph: &Graph<i32>, objective: Objective) -> Option<(i32, Vec<usize>)> {
+    let n = graph.len();
+    if n == 0 {
+        return None;
+    }
+    let full = (1usize << n) - 1;
+    let mut best: Vec<Option<i32>> = vec![None; (full + 1) * n];
+    let mut previous = vec![usize::MAX; (full + 1) * n];
+    for i in 0..n {
+        best[(1 << i) * n + i] = Some(0);
+    }
+    for mask in 1..=full {
+        for last in 0..n {
+            let current = match best[mask * n + last] {
+                Some(v) => v,
+                None => continue,
+            };
+            for (next, &distance) in graph.neighbors(last) {
+                if mask & (1 << next) != 0 {
+                    continue;
+                }
+                let candidate = current + distance;
+                let index = (mask | (1 << next)) * n + next;
+                if best[index].is_none_or(|v| objective.is_better(candidate, v)) {
+                    best[index] = Some(candidate);
+                    previous[index] = last;
+                }
+            }
+        }
+    }
+
+    let (mut last, distance) = (0..n)
+        .filter_map(|i| best[full * n + i].map(|d| (i, d)))
+        .reduce(|l, r| if objective.is_better(r.1, l.1) { r } else { l })?;
+    let mut mask = full;
+    let mut path = vec![last];
+    while mask.count_ones() > 1 {
+        let before = previous[mask * n + last];
+        mask &= !(1 << last);
+        last = before;
+        path.push(last);
+    }
+    path.reverse();
+    Some((distance, path))
 }
 
 fn main() {
-    let conns = aoc::get_input(15, 9)
-        .trim()
-        .split('\n')
-        .map(|l| {
+    let input = aoc::get_input(15, 9);
+    let graph = Graph::from_edges(
+        EdgeKind::Undirected,
+        Storage::Dense,
+        input.trim().split('\n').map(|l| {
             let mut it = l.split(' ').step_by(2);
             (
-                it.next().unwrap().to_owned(),
-                it.next().unwrap().to_owned(),
+                it.next().unwrap(),
+                it.next().unwrap(),
                 it.next().unwrap().parse::<i32>().unwrap(),
             )
-        })
-        .collect::<Vec<_>>();
-    let graph = to_connection_map(&conns);
+        }),
+    );
+    if std::env::args().any(|a| a == "--dot") {
+        print!("{}", graph.to_dot(|d| Some(d.to_string())));
+        return;
+    }
 
-    let (min, max) = min_max_path(&graph);
-    println!("Part 1: {}", min);
-    println!("Part 2: {}", max);
+    let (min, min_path) = best_path(&graph, Objective::Shortest).expect("No cities provided");
+    let (max, max_path) = best_path(&graph, Objective::Longest).expect("No cities provided");
+    let route = |path: &[usize]| {
+        path.iter()
+            .map(|&i| graph.name(i))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    };
+    println!("Part 1: {} ({})", min, route(&min_path));
+    println!("Part 2: {} ({})", max, route(&max_path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = aoc::XorShift::new(0x2015_0009);
+        for _ in 0..300 {
+            let n = 1 + rng.below(7) as usize;
+            let mut graph = Graph::new(EdgeKind::Undirected, Storage::Dense);
+            for i in 0..n {
+                graph.add_node(&i.to_string());
+            }
+            // Leave out about a third of the edges, so some graphs have no route at all
+            for i in 0..n {
+                for j in i + 1..n {
+                    if rng.below(3) != 0 {
+                        let distance = 1 + rng.below(100) as i32;
+                        graph.add_edge(&i.to_string(), &j.to_string(), distance);
+                    }
+                }
+            }
+            let shortest = best_path(&graph, Objective::Shortest);
+            let longest = best_path(&graph, Objective::Longest);
+            for (distance, path) in shortest.iter().chain(&longest) {
+                let mut visited = path.clone();
+                visited.sort_unstable();
+                assert_eq!(
+                    visited,
+                    (0..n).collect::<Vec<_>>(),
+                    "not a route: {:?}",
+                    path
+                );
+                assert_eq!(weight(&graph, path), Some(*distance));
+            }
+            assert_eq!(
+                shortest.zip(longest).map(|((min, _), (max, _))| (min, max)),
+                min_max_path(&graph),
+                "Held-Karp disagrees with brute force on {} cities",
+                n
+            );
+        }
+    }
 }
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
 // TODO this is a very implicit way of calculating the result, try to rewrite this more functionally?
 fn look_say(seq: &[i8]) -> Vec<i8> {
     let mut result = vec![];
@@ -14,7 +18,7 @@ fn look_say(seq: &[i8]) -> Vec<i8> {
             continue;
         } else {
             result.push(count as i8);
-            result.push(*current.unwrap() as i8);
+            result.push(*current.unwrap());
             current = next;
             count = 1;
         }
@@ -26,6 +30,277 @@ fn advance(seq: &[i8], n: u32) -> Vec<i8> {
     (0..n).fold(seq.to_owned(), |acc, _| look_say(&acc))
 }
 
+// Conway's audioactive elements: name, sequence and the elements it decays into in one step
+const ELEMENTS: [(&str, &str, &[&str]); 92] = [
+    ("H", "22", &["H"]),
+    (
+        "He",
+        "13112221133211322112211213322112",
+        &["Hf", "Pa", "H", "Ca", "Li"],
+    ),
+    ("Li", "312211322212221121123222112", &["He"]),
+    (
+        "Be",
+        "111312211312113221133211322112211213322112",
+        &["Ge", "Ca", "Li"],
+    ),
+    ("B", "1321132122211322212221121123222112", &["Be"]),
+    ("C", "3113112211322112211213322112", &["B"]),
+    ("N", "111312212221121123222112", &["C"]),
+    ("O", "132112211213322112", &["N"]),
+    ("F", "31121123222112", &["O"]),
+    ("Ne", "111213322112", &["F"]),
+    ("Na", "123222112", &["Ne"]),
+    ("Mg", "3113322112", &["Pm", "Na"]),
+    ("Al", "1113222112", &["Mg"]),
+    ("Si", "1322112", &["Al"]),
+    ("P", "311311222112", &["Ho", "Si"]),
+    ("S", "1113122112", &["P"]),
+    ("Cl", "132112", &["S"]),
+    ("Ar", "3112", &["Cl"]),
+    ("K", "1112", &["Ar"]),
+    ("Ca", "12", &["K"]),
+    ("Sc", "3113112221133112", &["Ho", "Pa", "H", "Ca", "Co"]),
+    ("Ti", "11131221131112", &["Sc"]),
+    ("V", "13211312", &["Ti"]),
+    ("Cr", "31132", &["V"]),
+    ("Mn", "111311222112", &["Cr", "Si"]),
+    ("Fe", "13122112", &["Mn"]),
+    ("Co", "32112", &["Fe"]),
+    ("Ni", "11133112", &["Zn", "Co"]),
+    ("Cu", "131112", &["Ni"]),
+    ("Zn", "312", &["Cu"]),
+    (
+        "Ga",
+        "13221133122211332",
+        &["Eu", "Ca", "Ac", "H", "Ca", "Zn"],
+    ),
+    ("Ge", "31131122211311122113222", &["Ho", "Ga"]),
+    ("As", "11131221131211322113322112", &["Ge", "Na"]),
+    ("Se", "13211321222113222112", &["As"]),
+    ("Br", "3113112211322112", &["Se"]),
+    ("Kr", "11131221222112", &["Br"]),
+    ("Rb", "1321122112", &["Kr"]),
+    ("Sr", "3112112", &["Rb"]),
+    ("Y", "1112133", &["Sr", "U"]),
+    ("Zr", "12322211331222113112211", &["Y", "H", "Ca", "Tc"]),
+    ("Nb", "1113122113322113111221131221", &["Er", "Zr"]),
+    ("Mo", "13211322211312113211", &["Nb"]),
+    ("Tc", "311322113212221", &["Mo"]),
+    ("Ru", "132211331222113112211", &["Eu", "Ca", "Tc"]),
+    ("Rh", "311311222113111221131221", &["Ho", "Ru"]),
+    ("Pd", "111312211312113211", &["Rh"]),
+    ("Ag", "132113212221", &["Pd"]),
+    ("Cd", "3113112211", &["Ag"]),
+    ("In", "11131221", &["Cd"]),
+    ("Sn", "13211", &["In"]),
+    ("Sb", "3112221", &["Pm", "Sn"]),
+    ("Te", "1322113312211", &["Eu", "Ca", "Sb"]),
+    ("I", "311311222113111221", &["Ho", "Te"]),
+    ("Xe", "11131221131211", &["I"]),
+    ("Cs", "13211321", &["Xe"]),
+    ("Ba", "311311", &["Cs"]),
+    ("La", "11131", &["Ba"]),
+    ("Ce", "1321133112", &["La", "H", "Ca", "Co"]),
+    ("Pr", "31131112", &["Ce"]),
+    ("Nd", "111312", &["Pr"]),
+    ("Pm", "132", &["Nd"]),
+    ("Sm", "311332", &["Pm", "Ca", "Zn"]),
+    ("Eu", "1113222", &["Sm"]),
+    ("Gd", "13221133112", &["Eu", "Ca", "Co"]),
+    ("Tb", "3113112221131112", &["Ho", "Gd"]),
+    ("Dy", "111312211312", &["Tb"]),
+    ("Ho", "1321132", &["Dy"]),
+    ("Er", "311311222", &["Ho", "Pm"]),
+    ("Tm", "11131221133112", &["Er", "Ca", "Co"]),
+    ("Yb", "1321131112", &["Tm"]),
+    ("Lu", "311312", &["Yb"]),
+    ("Hf", "11132", &["Lu"]),
+    (
+        "Ta",
+        "13112221133211322112211213322113",
+        &["Hf", "Pa", "H", "Ca", "W"],
+    ),
+    ("W", "312211322212221121123222113", &["Ta"]),
+    (
+        "Re",
+        "111312211312113221133211322112211213322113",
+        &["Ge", "Ca", "W"],
+    ),
+    ("Os", "1321132122211322212221121123222113", &["Re"]),
+    ("Ir", "3113112211322112211213322113", &["Os"]),
+    ("Pt", "111312212221121123222113", &["Ir"]),
+    ("Au", "132112211213322113", &["Pt"]),
+    ("Hg", "31121123222113", &["Au"]),
+    ("Tl", "111213322113", &["Hg"]),
+    ("Pb", "123222113", &["Tl"]),
+    ("Bi", "3113322113", &["Pm", "Pb"]),
+    ("Po", "1113222113", &["Bi"]),
+    ("At", "1322113", &["Po"]),
+    ("Rn", "311311222113", &["Ho", "At"]),
+    ("Fr", "1113122113", &["Rn"]),
+    ("Ra", "132113", &["Fr"]),
+    ("Ac", "3113", &["Ra"]),
+    ("Th", "1113", &["Ac"]),
+    ("Pa", "13", &["Th"]),
+    ("U", "3", &["Pa"]),
+];
+
+// Conway's splitting theorem only holds for strings at least this many steps old
+const YOUNG_STEPS: u32 = 2;
+
+/// Runs of a sequence as (digit, length).
+fn runs(seq: &[i8]) -> Vec<(i8, usize)> {
+    seq.chunk_by(|a, b| a == b)
+        .map(|r| (r[0], r.len()))
+        .collect()
+}
+
+// Conway's splitting theorem: a string at least two days old splits as L.R, so that L and R
+// never interact again, exactly when
+//   L ends in n >= 4 and R starts with m <= 3, or
+//   L ends in 2 and R starts with 1^1 X^1, 1^3, 3^1 X^(not 3) or n^1 (n >= 4), or
+//   L does not end in 2 and R starts with 2^2 followed by one of those or nothing.
+// Runs are written digit^length, X is any other digit.
+fn splits_between(last: i8, right: &[i8]) -> bool {
+    let runs = runs(right);
+    let run = |i: usize| runs.get(i).copied();
+    let after_two = |start: usize| match run(start) {
+        Some((1, 1)) => run(start + 1).is_some_and(|(_, k)| k == 1),
+        Some((1, 3)) => true,
+        Some((3, 1)) => run(start + 1).is_none_or(|(_, k)| k != 3),
+        Some((n, 1)) => n >= 4,
+        _ => false,
+    };
+    match (last, run(0)) {
+        (_, None) => true,
+        (n, Some((m, _))) if n >= 4 && m <= 3 => true,
+        (2, _) => after_two(0),
+        (_, Some((2, 2))) => run(1).is_none() || after_two(1),
+        _ => false,
+    }
+}
+
+/// Pieces of a sequence at least two days old that evolve independently.
+fn atoms(seq: &[i8]) -> Vec<&[i8]> {
+    let mut result = vec![];
+    let mut start = 0;
+    for i in 1..seq.len() {
+        if splits_between(seq[i - 1], &seq[i..]) {
+            result.push(&seq[start..i]);
+            start = i;
+        }
+    }
+    if start < seq.len() {
+        result.push(&seq[start..]);
+    }
+    result
+}
+
+/// Conway's elements, followed by any other atoms met on the way, e.g. transuranic ones.
+struct Elements {
+    sequences: Vec<Vec<i8>>,
+    indices: HashMap<Vec<i8>, usize>,
+    decays: Vec<Option<Vec<usize>>>,
+}
+
+impl Elements {
+    fn new() -> Elements {
+        let index = |name: &str| ELEMENTS.iter().position(|e| e.0 == name).unwrap();
+        let sequences = ELEMENTS
+            .iter()
+            .map(|e| e.1.bytes().map(|b| (b - b'0') as i8).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        Elements {
+            indices: sequences
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (s.clone(), i))
+                .collect(),
+            sequences,
+            decays: ELEMENTS
+                .iter()
+                .map(|e| Some(e.2.iter().map(|&n| index(n)).collect()))
+                .collect(),
+        }
+    }
+
+    fn intern(&mut self, seq: &[i8]) -> usize {
+        if let Some(&i) = self.indices.get(seq) {
+            return i;
+        }
+        self.sequences.push(seq.to_owned());
+        self.decays.push(None);
+        self.indices
+            .insert(seq.to_owned(), self.sequences.len() - 1);
+        self.sequences.len() - 1
+    }
+
+    fn split(&mut self, seq: &[i8]) -> Vec<usize> {
+        atoms(seq).into_iter().map(|a| self.intern(a)).collect()
+    }
+
+    fn decay(&mut self, atom: usize) -> Vec<usize> {
+        if let Some(decay) = &self.decays[atom] {
+            return decay.clone();
+        }
+        let decay = self.split(&look_say(&self.sequences[atom]));
+        self.decays[atom] = Some(decay.clone());
+        decay
+    }
+
+    fn step(&mut self, counts: &[BigUint]) -> Vec<BigUint> {
+        let mut result = vec![];
+        for (atom, count) in counts.iter().enumerate() {
+            for d in self.decay(atom) {
+                if d >= result.len() {
+                    result.resize(d + 1, BigUint::default());
+                }
+                result[d] += count;
+            }
+        }
+        result
+    }
+
+    fn length(&self, counts: &[BigUint]) -> BigUint {
+        counts
+            .iter()
+            .zip(self.sequences.iter())
+            .map(|(count, seq)| count * seq.len())
+            .sum()
+    }
+}
+
+// Atom counts of a sequence once it is old enough to split.
+struct Decomposition {
+    start: Vec<i8>,
+    counts: Vec<BigUint>,
+}
+
+impl Decomposition {
+    fn new(elements: &mut Elements, seq: &[i8]) -> Decomposition {
+        let mut counts = vec![];
+        for atom in elements.split(&advance(seq, YOUNG_STEPS)) {
+            if atom >= counts.len() {
+                counts.resize(atom + 1, BigUint::default());
+            }
+            counts[atom] += 1u32;
+        }
+        Decomposition {
+            start: seq.to_owned(),
+            counts,
+        }
+    }
+
+    fn length_after(&self, elements: &mut Elements, n: u32) -> BigUint {
+        if n < YOUNG_STEPS {
+            return BigUint::from(advance(&self.start, n).len());
+        }
+        let counts = (YOUNG_STEPS..n).fold(self.counts.clone(), |acc, _| elements.step(&acc));
+        elements.length(&counts)
+    }
+}
+
 fn main() {
     let sequence = aoc::get_input(15, 10)
         .trim()
@@ -34,6 +309,65 @@ fn main() {
         .collect::<Option<Vec<_>>>()
         .expect("Invalid input");
 
-    println!("Part 1: {}", advance(&sequence, 40).len());
-    println!("Part 2: {}", advance(&sequence, 50).len());
+    let mut elements = Elements::new();
+    let decomposition = Decomposition::new(&mut elements, &sequence);
+
+    println!("Part 1: {}", decomposition.length_after(&mut elements, 40));
+    println!("Part 2: {}", decomposition.length_after(&mut elements, 50));
+    if let Some(n) = std::env::args().nth(1) {
+        let n = n.parse().expect("Invalid number of iterations");
+        println!(
+            "After {} iterations: {}",
+            n,
+            decomposition.length_after(&mut elements, n)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_split_as_tabulated() {
+        let mut elements = Elements::new();
+        for (i, element) in ELEMENTS.iter().enumerate() {
+            let next = look_say(&elements.sequences[i].clone());
+            let names = elements
+                .split(&next)
+                .into_iter()
+                .map(|a| ELEMENTS.get(a).map_or("?", |e| e.0))
+                .collect::<Vec<_>>();
+            assert_eq!(names, element.2, "{} decays differently", element.0);
+        }
+    }
+
+    #[test]
+    fn agrees_with_direct_method() {
+        let fixed = ["1", "2121", "2222", "33333", "1113222113", "3", "22"]
+            .iter()
+            .map(|s| s.bytes().map(|b| (b - b'0') as i8).collect::<Vec<_>>())
+            .map(|s| (s, 30));
+        let mut rng = aoc::XorShift::new(10);
+        let random = (0..200).map(|_| {
+            let len = 1 + rng.below(10) as usize;
+            let seed = (0..len).map(|_| 1 + rng.below(3) as i8).collect();
+            (seed, 20)
+        });
+        let mut elements = Elements::new();
+        for (seed, steps) in fixed.chain(random) {
+            let decomposition = Decomposition::new(&mut elements, &seed);
+            let mut direct = seed.clone();
+            for n in 0..=steps {
+                assert_eq!(
+                    decomposition.length_after(&mut elements, n),
+                    BigUint::from(direct.len()),
+                    "{:?} after {} steps",
+                    seed,
+                    n
+                );
+                direct = look_say(&direct);
+            }
+        }
+    }
 }
@@ -1,53 +1,138 @@
-fn increment(s: &mut [char]) {
-    for i in 0..s.len() {
-        let v = &mut s[s.len() - 1 - i];
-        if *v == 'z' {
-            *v = 'a';
-        } else {
-            *v = ((*v as u8) + 1) as char;
-            break;
+use std::collections::HashSet;
+
+use anyhow::Context;
+
+#[derive(Debug, Clone)]
+struct Policy {
+    alphabet: Vec<char>,
+    forbidden: Vec<char>,
+    straight: usize,
+    pairs: usize,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            alphabet: ('a'..='z').collect(),
+            forbidden: vec!['i', 'o', 'l'],
+            straight: 3,
+            pairs: 2,
         }
     }
 }
 
-fn has_straight(s: &[char], size: usize) -> bool {
-    s.windows(size)
-        .any(|cs| cs[1] as u8 == (cs[0] as u8) + 1 && cs[2] as u8 == (cs[1] as u8) + 1)
+impl Policy {
+    // Passwords are kept as indices into the alphabet
+    fn has_straight(&self, s: &[usize]) -> bool {
+        self.straight <= 1
+            || s.windows(self.straight)
+                .any(|w| w.windows(2).all(|p| p[1] == p[0] + 1))
+    }
+
+    fn has_pairs(&self, s: &[usize]) -> bool {
+        let pairs = s
+            .windows(2)
+            .filter(|p| p[0] == p[1])
+            .map(|p| p[0])
+            .collect::<HashSet<_>>();
+        pairs.len() >= self.pairs
+    }
+
+    fn is_valid(&self, s: &[usize]) -> bool {
+        self.has_straight(s) && self.has_pairs(s)
+    }
+
+    fn passwords_after(&self, s: &str) -> anyhow::Result<Passwords<'_>> {
+        Passwords::new(self, s)
+    }
 }
 
-fn has_n_doubles(s: &[char], n: i32) -> bool {
-    let mut found = 0;
-    let mut skip = false;
-    for i in 0..(s.len() - 1) {
-        if skip {
-            skip = false;
-        } else {
-            if s[i] == s[i + 1] {
-                found += 1;
-                skip = true;
+// Iterates over valid passwords, candidates are built from allowed letters only.
+struct Passwords<'a> {
+    policy: &'a Policy,
+    // alphabet indices of the allowed letters, in alphabet order
+    allowed: Vec<usize>,
+    // indices into `allowed`
+    next_candidate: Option<Vec<usize>>,
+}
+
+impl<'a> Passwords<'a> {
+    fn new(policy: &'a Policy, s: &str) -> anyhow::Result<Passwords<'a>> {
+        let allowed = (0..policy.alphabet.len())
+            .filter(|&i| !policy.forbidden.contains(&policy.alphabet[i]))
+            .collect::<Vec<_>>();
+        let start = s
+            .chars()
+            .map(|c| policy.alphabet.iter().position(|&a| a == c))
+            .collect::<Option<Vec<_>>>()
+            .context("Character outside of the alphabet")?;
+
+        let next_candidate = Passwords::first_candidate(&start, &allowed);
+        Ok(Passwords {
+            policy,
+            allowed,
+            next_candidate,
+        })
+    }
+
+    // Smallest password after `start` made of allowed letters only, when `start` contains a
+    // forbidden letter the whole block of passwords sharing that prefix is skipped.
+    fn first_candidate(start: &[usize], allowed: &[usize]) -> Option<Vec<usize>> {
+        let mut digits = vec![0; start.len()];
+        for (i, &c) in start.iter().enumerate() {
+            if let Some(d) = allowed.iter().position(|&a| a == c) {
+                digits[i] = d;
+                continue;
             }
+            return match allowed.iter().position(|&a| a > c) {
+                Some(d) => {
+                    digits[i] = d;
+                    Some(digits)
+                }
+                None => Passwords::increment(&digits[..i], allowed.len()).map(|mut prefix| {
+                    prefix.resize(start.len(), 0);
+                    prefix
+                }),
+            };
         }
+        Passwords::increment(&digits, allowed.len())
+    }
+
+    fn increment(digits: &[usize], base: usize) -> Option<Vec<usize>> {
+        let mut result = digits.to_owned();
+        for d in result.iter_mut().rev() {
+            if *d + 1 < base {
+                *d += 1;
+                return Some(result);
+            }
+            *d = 0;
+        }
+        None
+    }
+
+    fn to_alphabet(&self, digits: &[usize]) -> Vec<usize> {
+        digits.iter().map(|&d| self.allowed[d]).collect()
     }
-    found >= n
 }
 
-fn find_next_password(s: &mut [char]) {
-    loop {
-        if has_n_doubles(s, 2)
-            && has_straight(s, 3)
-            && !s.iter().any(|&c| c == 'i' || c == 'o' || c == 'l')
-        {
-            return;
+impl Iterator for Passwords<'_> {
+    type Item = String;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let candidate = self.next_candidate.take()?;
+            self.next_candidate = Passwords::increment(&candidate, self.allowed.len());
+            let password = self.to_alphabet(&candidate);
+            if self.policy.is_valid(&password) {
+                return Some(password.iter().map(|&i| self.policy.alphabet[i]).collect());
+            }
         }
-        increment(s);
     }
 }
 
 fn main() {
-    let mut s = aoc::get_input(15, 11).trim().chars().collect::<Vec<_>>();
-    find_next_password(&mut s);
-    println!("Part 1: {}", s.iter().collect::<String>());
-    increment(&mut s);
-    find_next_password(&mut s);
-    println!("Part 2: {}", s.iter().collect::<String>());
+    let input = aoc::get_input(15, 11);
+    let policy = Policy::default();
+    let mut passwords = policy.passwords_after(input.trim()).expect("Invalid input");
+    println!("Part 1: {}", passwords.next().expect("No valid password"));
+    println!("Part 2: {}", passwords.next().expect("No valid password"));
 }
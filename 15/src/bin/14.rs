@@ -1,11 +1,13 @@
 use anyhow::Context;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::io::{BufWriter, Write};
 use std::str::FromStr;
 
 const COMPETITION_TIME: i32 = 2503;
 
 struct Reindeer {
+    name: String,
     speed: i32,
     run_time: i32,
     rest_time: i32,
@@ -16,13 +18,14 @@ impl FromStr for Reindeer {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
             static ref REINDEER_REGEX: Regex =
-                Regex::new(r"[A-Za-z]* can fly (?P<speed>\d+) km/s for (?P<run_time>\d+) seconds, but then must rest for (?P<rest_time>\d+) seconds.")
+                Regex::new(r"(?P<name>[A-Za-z]*) can fly (?P<speed>\d+) km/s for (?P<run_time>\d+) seconds, but then must rest for (?P<rest_time>\d+) seconds.")
                     .unwrap();
         }
         let parts = REINDEER_REGEX
             .captures(s)
             .context("Invalid reindeer specification")?;
         Ok(Reindeer {
+            name: parts["name"].to_owned(),
             speed: parts["speed"].parse()?,
             run_time: parts["run_time"].parse()?,
             rest_time: parts["rest_time"].parse()?,
@@ -30,66 +33,53 @@ impl FromStr for Reindeer {
     }
 }
 
-fn distance_at(reindeer: &Reindeer, seconds: i32) -> i32 {
-    let base = reindeer.run_time + reindeer.rest_time;
-    let distance_base = (seconds / base) * reindeer.speed * reindeer.run_time;
-    let remaining = seconds % base;
-    if remaining > reindeer.run_time {
-        distance_base + reindeer.speed * reindeer.run_time
-    } else {
-        distance_base + reindeer.speed * remaining
-    }
+// At most `seconds` of running at `speed`, both fit an i32 so the product fits an i64
+fn distance_at(reindeer: &Reindeer, seconds: i32) -> i64 {
+    let (seconds, run_time) = (seconds as i64, reindeer.run_time as i64);
+    let base = (run_time + reindeer.rest_time as i64).max(1);
+    let running = seconds / base * run_time + (seconds % base).min(run_time);
+    running * reindeer.speed as i64
 }
 
-fn max_at<T>(values: &[T]) -> Option<usize>
-where
-    T: Ord,
-{
-    values
-        .iter()
-        .enumerate()
-        .max_by(|(_, l), (_, r)| l.cmp(r))
-        .map(|(index, _)| index)
+fn csv_header(reindeers: &[Reindeer]) -> String {
+    let mut header = vec!["second".to_owned()];
+    header.extend(reindeers.iter().map(|r| format!("{} distance", r.name)));
+    header.extend(reindeers.iter().map(|r| format!("{} points", r.name)));
+    header.join(",")
 }
 
-fn points_at(reindeers: &[Reindeer], seconds: i32) -> Vec<i32> {
-    if reindeers.is_empty() {
-        return vec![];
-    }
+fn csv_row(second: i32, distances: &[i64], points: &[i32]) -> String {
+    std::iter::once(second as i64)
+        .chain(distances.iter().copied())
+        .chain(points.iter().map(|&p| p as i64))
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
 
-    enum State {
-        Resting(i32),
-        Running(i32),
-    }
-    let mut scores = vec![0; reindeers.len()];
+// Every reindeer sharing the lead after a second gets a point, `on_second` sees the distances and
+// points after each second
+fn race(
+    reindeers: &[Reindeer],
+    seconds: i32,
+    mut on_second: impl FnMut(i32, &[i64], &[i32]),
+) -> Vec<i32> {
+    let mut points = vec![0; reindeers.len()];
     let mut distances = vec![0; reindeers.len()];
-    let mut states = reindeers
-        .iter()
-        .map(|r| State::Running(r.run_time))
-        .collect::<Vec<_>>();
-    for _ in 0..seconds {
-        for i in 0..reindeers.len() {
-            match states[i] {
-                State::Resting(v) => {
-                    if v > 1 {
-                        states[i] = State::Resting(v - 1);
-                    } else {
-                        states[i] = State::Running(reindeers[i].run_time)
-                    }
-                }
-                State::Running(v) => {
-                    distances[i] += reindeers[i].speed;
-                    if v > 1 {
-                        states[i] = State::Running(v - 1);
-                    } else {
-                        states[i] = State::Resting(reindeers[i].rest_time)
-                    }
+    for second in 1..=seconds {
+        for (d, r) in distances.iter_mut().zip(reindeers) {
+            *d = distance_at(r, second);
+        }
+        if let Some(&lead) = distances.iter().max() {
+            for (p, &d) in points.iter_mut().zip(distances.iter()) {
+                if d == lead {
+                    *p += 1;
                 }
             }
         }
-        scores[max_at(&distances).unwrap()] += 1;
+        on_second(second, &distances, &points);
     }
-    scores
+    points
 }
 
 fn main() {
@@ -97,19 +87,85 @@ fn main() {
         .split('\n')
         .filter_map(|s| Reindeer::from_str(s).ok())
         .collect::<Vec<_>>();
+    let mut seconds = COMPETITION_TIME;
+    let mut csv = false;
+    for arg in std::env::args().skip(1) {
+        if arg == "--csv" {
+            csv = true;
+        } else {
+            seconds = arg
+                .parse()
+                .ok()
+                .filter(|&s| s > 0)
+                .expect("Race length has to be a positive number of seconds");
+        }
+    }
+
+    if csv {
+        let mut out = BufWriter::new(std::io::stdout().lock());
+        writeln!(out, "{}", csv_header(&reindeers)).expect("Cannot write CSV");
+        race(&reindeers, seconds, |second, distances, points| {
+            writeln!(out, "{}", csv_row(second, distances, points)).expect("Cannot write CSV")
+        });
+        return;
+    }
     println!(
         "Part 1: {}",
         reindeers
             .iter()
-            .map(|r| distance_at(r, COMPETITION_TIME))
+            .map(|r| distance_at(r, seconds))
             .max()
             .expect("No reindeer provided")
     );
     println!(
         "Part 2: {}",
-        points_at(&reindeers, COMPETITION_TIME)
+        race(&reindeers, seconds, |_, _, _| {})
             .iter()
             .max()
             .expect("No reindeer provided")
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
+Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.";
+
+    #[test]
+    fn scores_example_race() {
+        let reindeers = EXAMPLE
+            .lines()
+            .map(|l| l.parse().unwrap())
+            .collect::<Vec<Reindeer>>();
+        let mut rows = vec![];
+        let points = race(&reindeers, 1000, |second, distances, points| {
+            if second == 1 || second == 140 || second == 1000 {
+                rows.push(csv_row(second, distances, points));
+            }
+        });
+        assert_eq!(points, [312, 689]);
+        assert_eq!(
+            rows,
+            ["1,14,16,0,1", "140,182,176,1,139", "1000,1120,1056,312,689"]
+        );
+    }
+
+    #[test]
+    fn distances_do_not_wrap() {
+        let reindeer = EXAMPLE.lines().next().unwrap().parse::<Reindeer>().unwrap();
+        assert_eq!(distance_at(&reindeer, 1000), 1120);
+        let cycles = i32::MAX as i64 / 137;
+        assert_eq!(distance_at(&reindeer, i32::MAX), (cycles * 10 + 10) * 14);
+        let tireless = Reindeer {
+            speed: i32::MAX,
+            run_time: 1,
+            rest_time: 0,
+            ..reindeer
+        };
+        let max = i32::MAX as i64;
+        assert_eq!(distance_at(&tireless, i32::MAX), max * max);
+    }
+}
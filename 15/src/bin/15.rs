@@ -1,118 +1,209 @@
-use std::{
-    cmp,
-    ops::{Add, Mul},
-    str::FromStr,
-};
+use std::str::FromStr;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use lazy_static::lazy_static;
 use regex::Regex;
 
-#[derive(Debug, Default, Clone)]
-struct Recipe {
-    capacity: i64,
-    durability: i64,
-    flavor: i64,
-    texture: i64,
-    calories: i64,
+#[derive(Debug, Clone)]
+struct Ingredient {
+    name: String,
+    properties: Vec<(String, i64)>,
 }
 
-impl FromStr for Recipe {
+impl FromStr for Ingredient {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         lazy_static! {
-            static ref RECIPE_REGEX: Regex =
-                Regex::new(r"(?P<name>[^:]*): capacity (?P<capacity>-?[0-9]+), durability (?P<durability>-?[0-9]+), flavor (?P<flavor>-?[0-9]+), texture (?P<texture>-?[0-9]+), calories (?P<calories>[0-9]+)").unwrap();
+            static ref INGREDIENT_REGEX: Regex =
+                Regex::new(r"^(?P<name>[^:]+): (?P<properties>.*)$").unwrap();
+            static ref PROPERTY_REGEX: Regex =
+                Regex::new(r"^(?P<property>\w+) (?P<value>-?[0-9]+)$").unwrap();
         }
-        let parts = RECIPE_REGEX.captures(s).context("Invalid layout")?;
-        Ok(Recipe {
-            capacity: parts["capacity"].parse()?,
-            durability: parts["durability"].parse()?,
-            flavor: parts["flavor"].parse()?,
-            texture: parts["texture"].parse()?,
-            calories: parts["calories"].parse()?,
+        let parts = INGREDIENT_REGEX.captures(s).context("Invalid layout")?;
+        let properties = parts["properties"]
+            .split(", ")
+            .map(|p| {
+                let property = PROPERTY_REGEX.captures(p).context("Invalid property")?;
+                Ok((property["property"].to_owned(), property["value"].parse()?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Ingredient {
+            name: parts["name"].to_owned(),
+            properties,
         })
     }
 }
 
-impl Add<Recipe> for Recipe {
-    type Output = Recipe;
-    fn add(self, rhs: Recipe) -> Self::Output {
-        Recipe {
-            capacity: self.capacity + rhs.capacity,
-            durability: self.durability + rhs.durability,
-            flavor: self.flavor + rhs.flavor,
-            texture: self.texture + rhs.texture,
-            calories: self.calories + rhs.calories,
-        }
-    }
+#[derive(Debug, Clone)]
+struct Recipe {
+    score: i64,
+    amounts: Vec<i64>,
 }
 
-impl Mul<i64> for Recipe {
-    type Output = Recipe;
-    fn mul(self, rhs: i64) -> Self::Output {
-        Recipe {
-            capacity: self.capacity * rhs,
-            durability: self.durability * rhs,
-            flavor: self.flavor * rhs,
-            texture: self.texture * rhs,
-            calories: self.calories * rhs,
+#[derive(Debug, Clone)]
+struct Optimizer<'a> {
+    ingredients: &'a [Ingredient],
+    total: i64,
+    scored: Vec<usize>,
+    pinned: Vec<(usize, i64)>,
+    // Per property, the largest and smallest value among ingredients i.. for every i
+    suffix_max: Vec<Vec<i64>>,
+    suffix_min: Vec<Vec<i64>>,
+}
+
+impl<'a> Optimizer<'a> {
+    fn new(ingredients: &'a [Ingredient], total: i64) -> anyhow::Result<Optimizer<'a>> {
+        let n_properties = ingredients.first().map_or(0, |i| i.properties.len());
+        for ingredient in ingredients.iter().skip(1) {
+            let same = ingredient.properties.len() == n_properties
+                && ingredient
+                    .properties
+                    .iter()
+                    .zip(ingredients[0].properties.iter())
+                    .all(|(l, r)| l.0 == r.0);
+            if !same {
+                bail!("Ingredient {} has different properties", ingredient.name);
+            }
         }
+        let suffix = |f: fn(i64, i64) -> i64| {
+            let mut result: Vec<Vec<i64>> = vec![];
+            for ingredient in ingredients.iter().rev() {
+                let values = ingredient.properties.iter().map(|&(_, v)| v);
+                let row = match result.last() {
+                    Some(next) => values.zip(next.iter()).map(|(v, &n)| f(v, n)).collect(),
+                    None => values.collect(),
+                };
+                result.push(row);
+            }
+            result.reverse();
+            result
+        };
+        Ok(Optimizer {
+            ingredients,
+            total,
+            scored: (0..n_properties).collect(),
+            pinned: vec![],
+            suffix_max: suffix(i64::max),
+            suffix_min: suffix(i64::min),
+        })
     }
-}
 
-fn score(recipe: &Recipe, calories: Option<i64>) -> i64 {
-    if recipe.capacity <= 0
-        || recipe.durability <= 0
-        || recipe.flavor <= 0
-        || recipe.texture <= 0
-        || (calories.is_some() && recipe.calories != calories.unwrap())
-    {
-        0
-    } else {
-        recipe.capacity * recipe.durability * recipe.flavor * recipe.texture
+    fn property(&self, name: &str) -> anyhow::Result<usize> {
+        self.ingredients
+            .first()
+            .and_then(|i| i.properties.iter().position(|(p, _)| p == name))
+            .with_context(|| format!("Unknown property {}", name))
+    }
+
+    /// Leaves the property out of the score.
+    fn ignore(mut self, name: &str) -> anyhow::Result<Optimizer<'a>> {
+        let property = self.property(name)?;
+        self.scored.retain(|&p| p != property);
+        Ok(self)
+    }
+
+    /// Only accepts recipes where the property sums up to exactly `value`.
+    fn pin(mut self, name: &str, value: i64) -> anyhow::Result<Optimizer<'a>> {
+        let property = self.property(name)?;
+        self.pinned.push((property, value));
+        Ok(self)
     }
-}
 
-fn best_score(recipes: &[Recipe], amount: i64, calories: Option<i64>) -> i64 {
-    fn maximize(
-        recipes: &[Recipe],
-        amount: i64,
-        calories: Option<i64>,
-        acc: Recipe,
-        current_max: i64,
-    ) -> i64 {
-        let new_value = if recipes.len() == 1 {
-            let recipe = acc + recipes[0].clone() * amount;
-            score(&recipe, calories)
-        } else if amount == 0 {
-            score(&acc, calories)
+    fn score(&self, sums: &[i64]) -> i64 {
+        self.scored
+            .iter()
+            .fold(1i64, |acc, &p| acc.saturating_mul(sums[p].max(0)))
+    }
+
+    fn optimize(&self) -> Option<Recipe> {
+        if self.ingredients.is_empty() {
+            return None;
+        }
+        let mut sums = vec![0; self.suffix_max[0].len()];
+        let mut amounts = vec![0; self.ingredients.len()];
+        let mut best = None;
+        self.search(0, self.total, &mut sums, &mut amounts, &mut best);
+        best
+    }
+
+    fn search(
+        &self,
+        index: usize,
+        remaining: i64,
+        sums: &mut [i64],
+        amounts: &mut [i64],
+        best: &mut Option<Recipe>,
+    ) {
+        // Whatever remains is split among ingredients index.., which bounds every property sum
+        let reachable = self.pinned.iter().all(|&(p, value)| {
+            sums[p] + remaining * self.suffix_min[index][p] <= value
+                && value <= sums[p] + remaining * self.suffix_max[index][p]
+        });
+        if !reachable {
+            return;
+        }
+        let upper_bound = self.scored.iter().fold(1i64, |acc, &p| {
+            acc.saturating_mul((sums[p] + remaining * self.suffix_max[index][p]).max(0))
+        });
+        if best.as_ref().is_some_and(|b| upper_bound <= b.score) {
+            return;
+        }
+
+        let last = index + 1 == self.ingredients.len();
+        let amounts_to_try = if last {
+            remaining..=remaining
         } else {
-            let mut max_local = current_max;
-            for i in 0..=amount {
-                max_local = maximize(
-                    &recipes[1..],
-                    amount - i,
-                    calories,
-                    acc.clone() + recipes[0].clone() * i,
-                    max_local,
-                );
-            }
-            max_local
+            0..=remaining
         };
-        cmp::max(current_max, new_value)
+        for amount in amounts_to_try {
+            amounts[index] = amount;
+            for (sum, (_, value)) in sums.iter_mut().zip(&self.ingredients[index].properties) {
+                *sum += amount * value;
+            }
+            if last {
+                let score = self.score(sums);
+                if best.as_ref().is_none_or(|b| score > b.score) {
+                    *best = Some(Recipe {
+                        score,
+                        amounts: amounts.to_owned(),
+                    });
+                }
+            } else {
+                self.search(index + 1, remaining - amount, sums, amounts, best);
+            }
+            for (sum, (_, value)) in sums.iter_mut().zip(&self.ingredients[index].properties) {
+                *sum -= amount * value;
+            }
+        }
     }
-    maximize(recipes, amount, calories, Recipe::default(), 0)
+}
+
+fn describe(ingredients: &[Ingredient], recipe: &Recipe) -> String {
+    let amounts = ingredients
+        .iter()
+        .zip(recipe.amounts.iter())
+        .map(|(i, a)| format!("{}: {}", i.name, a))
+        .collect::<Vec<_>>();
+    format!("{} ({})", recipe.score, amounts.join(", "))
 }
 
 fn main() {
-    let recipes = aoc::get_input(15, 15)
+    let ingredients = aoc::get_input(15, 15)
         .trim()
         .split('\n')
-        .map(|l| Recipe::from_str(l))
+        .map(Ingredient::from_str)
         .collect::<anyhow::Result<Vec<_>>>()
         .expect("invalid input");
 
-    println!("Part 1: {}", best_score(&recipes, 100, None));
-    println!("Part 2: {}", best_score(&recipes, 100, Some(500)));
+    let optimizer = Optimizer::new(&ingredients, 100)
+        .and_then(|o| o.ignore("calories"))
+        .expect("invalid ingredients");
+    let best = optimizer.optimize().expect("No ingredients provided");
+    println!("Part 1: {}", describe(&ingredients, &best));
+    let best_pinned = optimizer
+        .pin("calories", 500)
+        .expect("invalid ingredients")
+        .optimize()
+        .expect("No recipe with 500 calories");
+    println!("Part 2: {}", describe(&ingredients, &best_pinned));
 }
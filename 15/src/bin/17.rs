@@ -1,11 +1,67 @@
-fn count_arrangements(parts: &[i32], value: i32, n_containers: i32) -> i32 {
-    if value == 0 && n_containers == 0 {
-        1
-    } else if parts.is_empty() || value < 0 || n_containers <= 0 {
-        0
-    } else {
-        count_arrangements(&parts[1..], value - parts[0], n_containers - 1)
-            + count_arrangements(&parts[1..], value, n_containers)
+const DEFAULT_TARGET: usize = 150;
+
+// ways[k] is the number of ways to fill the target with exactly k containers
+fn count_by_size(containers: &[usize], target: usize) -> Vec<u64> {
+    // ways[v][k] counts subsets of the containers seen so far with volume v using k containers
+    let mut ways = vec![vec![0u64; containers.len() + 1]; target + 1];
+    ways[0][0] = 1;
+    for (seen, &c) in containers.iter().enumerate() {
+        for v in (c..=target).rev() {
+            for k in (1..=seen + 1).rev() {
+                ways[v][k] += ways[v - c][k - 1];
+            }
+        }
+    }
+    ways.swap_remove(target)
+}
+
+// Combinations of container indices filling exactly the target, in lexicographic order
+struct Combinations<'a> {
+    containers: &'a [usize],
+    // reachable[i][v]: some subset of containers[i..] has volume v
+    reachable: Vec<Vec<bool>>,
+    stack: Vec<(usize, usize, Vec<usize>)>,
+}
+
+impl<'a> Combinations<'a> {
+    fn new(containers: &'a [usize], target: usize) -> Combinations<'a> {
+        let mut reachable = vec![vec![false; target + 1]; containers.len() + 1];
+        reachable[containers.len()][0] = true;
+        for i in (0..containers.len()).rev() {
+            for v in 0..=target {
+                reachable[i][v] = reachable[i + 1][v]
+                    || (v >= containers[i] && reachable[i + 1][v - containers[i]]);
+            }
+        }
+        Combinations {
+            containers,
+            reachable,
+            stack: vec![(0, target, vec![])],
+        }
+    }
+}
+
+impl Iterator for Combinations<'_> {
+    type Item = Vec<usize>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((i, remaining, chosen)) = self.stack.pop() {
+            if !self.reachable[i][remaining] {
+                continue;
+            }
+            // Zero-sized containers can still follow once the target is filled
+            if i == self.containers.len() {
+                return Some(chosen);
+            }
+            // Skipping is pushed first so that taking the container is explored first
+            self.stack.push((i + 1, remaining, chosen.clone()));
+            if self.containers[i] <= remaining {
+                let mut taken = chosen;
+                taken.push(i);
+                self.stack
+                    .push((i + 1, remaining - self.containers[i], taken));
+            }
+        }
+        None
     }
 }
 
@@ -13,20 +69,73 @@ fn main() {
     let containers = aoc::get_input(15, 17)
         .trim()
         .split('\n')
-        .map(|s| s.parse::<i32>())
+        .map(|s| s.parse::<usize>())
         .collect::<Result<Vec<_>, _>>()
         .expect("Invalid input");
+    let mut target = DEFAULT_TARGET;
+    let mut list = false;
+    for arg in std::env::args().skip(1) {
+        if arg == "--list" {
+            list = true;
+        } else {
+            target = arg.parse().expect("Invalid target");
+        }
+    }
+
+    let counts = count_by_size(&containers, target);
+    let min_size = counts.iter().position(|&c| c > 0);
+    println!("Part 1: {}", counts.iter().sum::<u64>());
+    println!("Part 2: {}", min_size.map_or(0, |k| counts[k]));
 
-    let mut min_arrangements = None;
-    let mut all_arrangements = 0;
-    for i in 1..=containers.len() {
-        let count = count_arrangements(&containers, 150, i as i32);
-        all_arrangements += count;
-        if min_arrangements.is_none() && count > 0 {
-            min_arrangements = Some(count);
+    if let Some(k) = min_size.filter(|_| list) {
+        println!("Smallest combinations:");
+        for combination in Combinations::new(&containers, target).filter(|c| c.len() == k) {
+            let sizes = combination
+                .iter()
+                .map(|&i| format!("#{} ({})", i, containers[i]))
+                .collect::<Vec<_>>();
+            println!("  {}", sizes.join(" + "));
         }
     }
+}
 
-    println!("Part 1: {}", all_arrangements);
-    println!("Part 2: {}", min_arrangements.unwrap_or(0));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_example_combinations() {
+        let containers = [20, 15, 10, 5, 5];
+        assert_eq!(
+            Combinations::new(&containers, 25).collect::<Vec<_>>(),
+            [vec![0, 3], vec![0, 4], vec![1, 2], vec![1, 3, 4]]
+        );
+        assert_eq!(count_by_size(&containers, 25), [0, 0, 3, 1, 0, 0]);
+    }
+
+    #[test]
+    fn listing_agrees_with_counts() {
+        let mut rng = aoc::XorShift::new(0x2015_0017);
+        for _ in 0..200 {
+            let containers = (0..rng.below(10))
+                .map(|_| rng.below(8) as usize)
+                .collect::<Vec<_>>();
+            let target = rng.below(20) as usize;
+            let mut listed = vec![0; containers.len() + 1];
+            for combination in Combinations::new(&containers, target) {
+                assert_eq!(
+                    combination.iter().map(|&i| containers[i]).sum::<usize>(),
+                    target
+                );
+                listed[combination.len()] += 1;
+            }
+            assert_eq!(
+                listed,
+                count_by_size(&containers, target),
+                "{:?} filling {}",
+                containers,
+                target
+            );
+        }
+    }
 }
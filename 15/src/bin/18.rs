@@ -1,172 +1,419 @@
 use std::str::FromStr;
+use std::time::Instant;
 
-use anyhow::{bail, Context};
+use aoc::{Automaton, Boundary, Rule};
 
-#[derive(Debug, Clone)]
-struct NormalGrid {
-    data: Vec<bool>,
-    n_columns: usize,
-}
+const STEPS: usize = 100;
+const BENCH_SIZE: usize = 1000;
+const BENCH_STEPS: [usize; 2] = [100, 10_000];
+const SETS_BENCH_MAX_STEPS: usize = 100;
 
-trait Grid {
-    fn get(&self, i: usize) -> bool;
-    fn set(&mut self, i: usize, v: bool);
-    fn size(&self) -> usize;
-    fn n_columns(&self) -> usize;
-}
+mod bits {
+    use std::str::FromStr;
 
-fn neighbors(i: usize, size: usize, n_cols: usize) -> Vec<usize> {
-    let mut result = vec![];
-    let to_index = |row: usize, col: usize| row * n_cols + col;
-    let (r, c) = (i / n_cols, i % n_cols);
-    let n_rows = size / n_cols;
-    if r > 0 {
-        result.push(to_index(r - 1, c));
-    }
-    if r < n_rows - 1 {
-        result.push(to_index(r + 1, c));
-    }
-    if r > 0 && c > 0 {
-        result.push(to_index(r - 1, c - 1));
-    }
-    if r < n_rows - 1 && c > 0 {
-        result.push(to_index(r + 1, c - 1));
+    use anyhow::bail;
+
+    // Conway's life packed 64 cells per word, rows padded to a whole number of words
+    #[derive(Debug, Clone)]
+    pub struct BitGrid {
+        n_rows: usize,
+        n_columns: usize,
+        words_per_row: usize,
+        data: Vec<u64>,
+        next: Vec<u64>,
+        pinned: Vec<(usize, usize)>,
     }
-    if c > 0 {
-        result.push(to_index(r, c - 1));
+
+    fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+        (a ^ b ^ c, (a & b) | (c & (a ^ b)))
     }
-    if c < n_cols - 1 {
-        result.push(to_index(r, c + 1));
+
+    fn half_add(a: u64, b: u64) -> (u64, u64) {
+        (a ^ b, a & b)
     }
-    if r > 0 && c < n_cols - 1 {
-        result.push(to_index(r - 1, c + 1));
+
+    impl BitGrid {
+        pub fn new(n_rows: usize, n_columns: usize) -> BitGrid {
+            let words_per_row = n_columns.div_ceil(64);
+            BitGrid {
+                n_rows,
+                n_columns,
+                words_per_row,
+                data: vec![0; n_rows * words_per_row],
+                next: vec![0; n_rows * words_per_row],
+                pinned: vec![],
+            }
+        }
+
+        pub fn n_rows(&self) -> usize {
+            self.n_rows
+        }
+
+        pub fn n_columns(&self) -> usize {
+            self.n_columns
+        }
+
+        pub fn set(&mut self, r: usize, c: usize, v: bool) {
+            let word = &mut self.data[r * self.words_per_row + c / 64];
+            if v {
+                *word |= 1 << (c % 64);
+            } else {
+                *word &= !(1 << (c % 64));
+            }
+        }
+
+        /// Keeps the cell on after every step.
+        pub fn pin(&mut self, r: usize, c: usize) {
+            self.set(r, c, true);
+            self.pinned.push((r, c));
+        }
+
+        pub fn count(&self) -> usize {
+            self.data.iter().map(|w| w.count_ones() as usize).sum()
+        }
+
+        fn word(&self, r: usize, i: usize) -> u64 {
+            self.data[r * self.words_per_row + i]
+        }
+
+        // (west, centre, east) neighbour planes of word i in row r, aligned with the word itself
+        fn planes(&self, r: usize, i: usize) -> (u64, u64, u64) {
+            let centre = self.word(r, i);
+            let before = if i > 0 { self.word(r, i - 1) >> 63 } else { 0 };
+            let after = if i + 1 < self.words_per_row {
+                self.word(r, i + 1) << 63
+            } else {
+                0
+            };
+            ((centre << 1) | before, centre, (centre >> 1) | after)
+        }
+
+        pub fn step(&mut self) {
+            let last_mask = match self.n_columns % 64 {
+                0 => u64::MAX,
+                n => (1 << n) - 1,
+            };
+            for r in 0..self.n_rows {
+                for i in 0..self.words_per_row {
+                    let (w, alive, e) = self.planes(r, i);
+                    let (nw, n, ne) = if r > 0 {
+                        self.planes(r - 1, i)
+                    } else {
+                        (0, 0, 0)
+                    };
+                    let (sw, s, se) = if r + 1 < self.n_rows {
+                        self.planes(r + 1, i)
+                    } else {
+                        (0, 0, 0)
+                    };
+                    // Bit-sliced sum of the 8 neighbours, a count of 8 wraps to 0 which is dead
+                    let (s1, c1) = full_add(nw, n, ne);
+                    let (s2, c2) = full_add(sw, s, se);
+                    let (s3, c3) = half_add(w, e);
+                    let (ones, c4) = full_add(s1, s2, s3);
+                    let (t, c5) = full_add(c1, c2, c3);
+                    let (twos, c6) = half_add(t, c4);
+                    let fours = c5 ^ c6;
+                    let mut next = twos & !fours & (ones | alive);
+                    if i + 1 == self.words_per_row {
+                        next &= last_mask;
+                    }
+                    self.next[r * self.words_per_row + i] = next;
+                }
+            }
+            std::mem::swap(&mut self.data, &mut self.next);
+            for (r, c) in self.pinned.clone() {
+                self.set(r, c, true);
+            }
+        }
     }
-    if r < n_rows - 1 && c < n_cols - 1 {
-        result.push(to_index(r + 1, c + 1));
+
+    impl FromStr for BitGrid {
+        type Err = anyhow::Error;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let rows = s.lines().collect::<Vec<_>>();
+            if rows.is_empty() {
+                bail!("No rows supplied");
+            }
+            let n_columns = rows[0].len();
+            if rows.iter().skip(1).any(|r| r.len() != n_columns) {
+                bail!("Not all rows of the same size");
+            }
+            let mut result = BitGrid::new(rows.len(), n_columns);
+            for (r, row) in rows.iter().enumerate() {
+                for (c, ch) in row.chars().enumerate() {
+                    match ch {
+                        '#' => result.set(r, c, true),
+                        '.' => {}
+                        _ => bail!("Invalid cell '{}'", ch),
+                    }
+                }
+            }
+            Ok(result)
+        }
     }
-    result
 }
 
-impl Grid for NormalGrid {
-    fn get(&self, i: usize) -> bool {
-        self.data[i]
-    }
+mod dense {
+    use std::str::FromStr;
+
+    use anyhow::bail;
 
-    fn set(&mut self, i: usize, v: bool) {
-        self.data[i] = v;
+    // One bool per cell, the stepper the bit-parallel one replaced
+    #[derive(Debug, Clone)]
+    pub struct DenseGrid {
+        n_rows: usize,
+        n_columns: usize,
+        cells: Vec<bool>,
+        next: Vec<bool>,
+        pinned: Vec<usize>,
     }
 
-    fn size(&self) -> usize {
-        self.data.len()
+    impl DenseGrid {
+        pub fn n_rows(&self) -> usize {
+            self.n_rows
+        }
+
+        pub fn n_columns(&self) -> usize {
+            self.n_columns
+        }
+
+        /// Keeps the cell on after every step.
+        pub fn pin(&mut self, r: usize, c: usize) {
+            let i = r * self.n_columns + c;
+            self.cells[i] = true;
+            self.pinned.push(i);
+        }
+
+        pub fn count(&self) -> usize {
+            self.cells.iter().filter(|&&c| c).count()
+        }
+
+        pub fn step(&mut self) {
+            let (n_rows, n_columns) = (self.n_rows, self.n_columns);
+            for r in 0..n_rows {
+                for c in 0..n_columns {
+                    let mut neighbours = 0;
+                    for nr in r.saturating_sub(1)..=(r + 1).min(n_rows - 1) {
+                        for nc in c.saturating_sub(1)..=(c + 1).min(n_columns - 1) {
+                            if (nr, nc) != (r, c) && self.cells[nr * n_columns + nc] {
+                                neighbours += 1;
+                            }
+                        }
+                    }
+                    let alive = self.cells[r * n_columns + c];
+                    self.next[r * n_columns + c] = neighbours == 3 || (alive && neighbours == 2);
+                }
+            }
+            std::mem::swap(&mut self.cells, &mut self.next);
+            for &i in &self.pinned {
+                self.cells[i] = true;
+            }
+        }
     }
 
-    fn n_columns(&self) -> usize {
-        self.n_columns
+    impl FromStr for DenseGrid {
+        type Err = anyhow::Error;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let rows = s.lines().collect::<Vec<_>>();
+            if rows.is_empty() {
+                bail!("No rows supplied");
+            }
+            let n_columns = rows[0].len();
+            if rows.iter().skip(1).any(|r| r.len() != n_columns) {
+                bail!("Not all rows of the same size");
+            }
+            let cells = rows
+                .iter()
+                .flat_map(|r| r.chars())
+                .map(|ch| match ch {
+                    '#' => Ok(true),
+                    '.' => Ok(false),
+                    _ => bail!("Invalid cell '{}'", ch),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(DenseGrid {
+                n_rows: rows.len(),
+                n_columns,
+                next: vec![false; cells.len()],
+                cells,
+                pinned: vec![],
+            })
+        }
     }
 }
 
-fn parse_cell(c: char) -> Option<bool> {
-    match c {
-        '#' => Some(true),
-        '.' => Some(false),
-        _ => None,
-    }
+use bits::BitGrid;
+use dense::DenseGrid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Implementation {
+    Sets,
+    Dense,
+    Bits,
 }
 
-impl FromStr for NormalGrid {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let rows = s
-            .split('\n')
-            .map(|r| r.chars().map(|c| parse_cell(c)).collect::<Option<Vec<_>>>())
-            .collect::<Option<Vec<_>>>()
-            .context("Invalid input characters")?;
-        if rows.is_empty() {
-            bail!("No rows supplied");
-        }
-        let n_columns = rows[0].len();
-        if rows.iter().skip(1).any(|v| v.len() != n_columns) {
-            bail!("Not all rows of the same size");
-        }
-        Ok(NormalGrid {
-            data: rows.into_iter().flatten().collect(),
-            n_columns,
-        })
-    }
+fn life() -> Rule {
+    Rule::from_str("B3/S23").unwrap()
 }
 
-fn step(grid: &mut impl Grid) {
-    let neighbors = (0..grid.size())
-        .map(|i| {
-            neighbors(i, grid.size(), grid.n_columns())
-                .into_iter()
-                .filter(|&n| grid.get(n))
-                .count()
-        })
-        .collect::<Vec<_>>();
-    for i in 0..grid.size() {
-        if grid.get(i) && (neighbors[i] < 2 || neighbors[i] > 3) {
-            grid.set(i, false);
-        } else if !grid.get(i) && neighbors[i] == 3 {
-            grid.set(i, true);
+// Lit lights after `steps` steps with or without the corners stuck on
+fn simulate(implementation: Implementation, board: &str, steps: usize, broken: bool) -> usize {
+    match implementation {
+        Implementation::Sets => {
+            let mut automaton =
+                Automaton::parse(board, life(), Boundary::Bounded).expect("invalid input");
+            if broken {
+                for corner in automaton.corners() {
+                    automaton.pin(corner, true);
+                }
+            }
+            automaton.run(steps);
+            automaton.n_alive()
+        }
+        Implementation::Dense => {
+            let mut grid = DenseGrid::from_str(board).expect("invalid input");
+            if broken {
+                let (r, c) = (grid.n_rows() - 1, grid.n_columns() - 1);
+                for (r, c) in [(0, 0), (0, c), (r, 0), (r, c)] {
+                    grid.pin(r, c);
+                }
+            }
+            for _ in 0..steps {
+                grid.step();
+            }
+            grid.count()
+        }
+        Implementation::Bits => {
+            let mut grid = BitGrid::from_str(board).expect("invalid input");
+            if broken {
+                let (r, c) = (grid.n_rows() - 1, grid.n_columns() - 1);
+                for (r, c) in [(0, 0), (0, c), (r, 0), (r, c)] {
+                    grid.pin(r, c);
+                }
+            }
+            for _ in 0..steps {
+                grid.step();
+            }
+            grid.count()
         }
     }
 }
 
-struct BrokenGrid(NormalGrid);
-
-impl Grid for BrokenGrid {
-    fn get(&self, i: usize) -> bool {
-        let (r, c) = (i / self.n_columns(), i % self.n_columns());
-        let n_rows = self.size() / self.n_columns();
-        if (r, c) == (0, 0)
-            || (r, c) == (n_rows - 1, 0)
-            || (r, c) == (0, self.n_columns() - 1)
-            || (r, c) == (n_rows - 1, self.n_columns() - 1)
-        {
-            true
-        } else {
-            self.0.get(i)
+fn random_board(n_rows: usize, n_columns: usize, seed: u64) -> String {
+    let mut rng = aoc::XorShift::new(seed);
+    let mut result = String::with_capacity(n_rows * (n_columns + 1));
+    for _ in 0..n_rows {
+        for _ in 0..n_columns {
+            result.push(if rng.below(2) == 0 { '#' } else { '.' });
         }
+        result.push('\n');
     }
+    result
+}
 
-    fn set(&mut self, i: usize, v: bool) {
-        self.0.data[i] = v;
+// The hash set automaton takes about 40s for 100 steps of the full board, so it only runs on
+// the shorter benchmark.
+fn bench() {
+    let board = random_board(BENCH_SIZE, BENCH_SIZE, 0x2545f4914f6cdd1d);
+    for steps in BENCH_STEPS {
+        let mut implementations = vec![Implementation::Dense, Implementation::Bits];
+        if steps <= SETS_BENCH_MAX_STEPS {
+            implementations.insert(0, Implementation::Sets);
+        }
+        let mut results = vec![];
+        for implementation in implementations {
+            let start = Instant::now();
+            let lit = simulate(implementation, &board, steps, false);
+            let elapsed = start.elapsed();
+            println!(
+                "{:?}, {}x{} board, {} steps: {} lit in {:?}",
+                implementation, BENCH_SIZE, BENCH_SIZE, steps, lit, elapsed
+            );
+            results.push((lit, elapsed));
+        }
+        assert!(
+            results.windows(2).all(|w| w[0].0 == w[1].0),
+            "Implementations disagree"
+        );
+        let (dense, bits) = (results[results.len() - 2].1, results[results.len() - 1].1);
+        println!(
+            "Bits are {:.1}x faster than dense after {} steps",
+            dense.as_secs_f64() / bits.as_secs_f64(),
+            steps
+        );
     }
+}
 
-    fn size(&self) -> usize {
-        self.0.size()
+fn main() {
+    let mut implementation = Implementation::Bits;
+    let mut run_bench = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--sets" => implementation = Implementation::Sets,
+            "--dense" => implementation = Implementation::Dense,
+            "--bits" => implementation = Implementation::Bits,
+            "--bench" => run_bench = true,
+            _ => panic!("Unknown argument {}", arg),
+        }
     }
-
-    fn n_columns(&self) -> usize {
-        self.0.n_columns()
+    if run_bench {
+        bench();
+        return;
     }
+
+    let input = aoc::get_input(15, 18);
+    let board = input.trim();
+    println!("Part 1: {}", simulate(implementation, board, STEPS, false));
+    println!("Part 2: {}", simulate(implementation, board, STEPS, true));
 }
 
-fn main() {
-    let state = NormalGrid::from_str(aoc::get_input(15, 18).trim()).expect("invalid input");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMPLEMENTATIONS: [Implementation; 3] = [
+        Implementation::Sets,
+        Implementation::Dense,
+        Implementation::Bits,
+    ];
+
+    const EXAMPLE: &str = "\
+.#.#.#
+...##.
+#....#
+..#...
+#.#..#
+####..";
 
-    let mut normal = state.clone();
-    for _ in 0..100 {
-        step(&mut normal);
+    #[test]
+    fn steps_example() {
+        for implementation in IMPLEMENTATIONS {
+            assert_eq!(simulate(implementation, EXAMPLE, 4, false), 4);
+            assert_eq!(simulate(implementation, EXAMPLE, 5, true), 17);
+        }
     }
-    println!(
-        "Part 1: {}",
-        (0..normal.size())
-            .map(|i| normal.get(i))
-            .filter(|&x| x)
-            .count()
-    );
-
-    let mut broken = BrokenGrid(state);
-    for _ in 0..100 {
-        step(&mut broken);
+
+    // 130 columns span three words with a partial last one, so the carries between words and
+    // the mask of the last word both matter
+    #[test]
+    fn implementations_agree_across_words() {
+        for (n_rows, n_columns, seed) in [(70, 130, 1), (5, 64, 2), (3, 65, 3), (64, 1, 4)] {
+            let board = random_board(n_rows, n_columns, seed);
+            for steps in [1, 2, 7, 30] {
+                for broken in [false, true] {
+                    let lit = IMPLEMENTATIONS
+                        .map(|implementation| simulate(implementation, &board, steps, broken));
+                    assert!(
+                        lit.iter().all(|&l| l == lit[0]),
+                        "{}x{} board after {} steps: {:?}",
+                        n_rows,
+                        n_columns,
+                        steps,
+                        lit
+                    );
+                }
+            }
+        }
     }
-    println!(
-        "Part 2: {}",
-        (0..broken.size())
-            .map(|i| broken.get(i))
-            .filter(|&x| x)
-            .count()
-    );
 }
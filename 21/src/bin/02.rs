@@ -1,8 +1,8 @@
 use anyhow::{bail, Result};
 use std::str::FromStr;
-use strum_macros::EnumString;
+use strum_macros::{Display, EnumString};
 
-#[derive(Debug, PartialEq, Eq, EnumString)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumString, Display)]
 enum Direction {
     #[strum(serialize = "forward")]
     Forward,
@@ -31,31 +31,122 @@ impl FromStr for Instruction {
     }
 }
 
-#[derive(Debug, Default)]
-struct AimPosition {
-    position: i32,
-    depth: i32,
-    aim: i32,
-}
+mod submarine {
+    use super::{Direction, Instruction};
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct State {
+        pub position: i32,
+        pub depth: i32,
+        pub aim: i32,
+    }
+
+    pub trait Semantics {
+        fn apply(&self, current: State, i: &Instruction) -> State;
+    }
+
+    /// Up and down change the depth directly.
+    pub struct Plain;
 
-fn follow(current: AimPosition, i: &Instruction) -> AimPosition {
-    match i.direction {
-        Direction::Forward => AimPosition {
-            position: current.position + i.amount,
-            depth: current.depth + i.amount * current.aim,
-            ..current
-        },
-        Direction::Down => AimPosition {
-            aim: current.aim + i.amount,
-            ..current
-        },
-        Direction::Up => AimPosition {
-            aim: current.aim - i.amount,
-            ..current
-        },
+    impl Semantics for Plain {
+        fn apply(&self, current: State, i: &Instruction) -> State {
+            match i.direction {
+                Direction::Forward => State {
+                    position: current.position + i.amount,
+                    ..current
+                },
+                Direction::Down => State {
+                    depth: current.depth + i.amount,
+                    ..current
+                },
+                Direction::Up => State {
+                    depth: current.depth - i.amount,
+                    ..current
+                },
+            }
+        }
+    }
+
+    /// Up and down change the aim, moving forward dives along it.
+    pub struct Aimed;
+
+    impl Semantics for Aimed {
+        fn apply(&self, current: State, i: &Instruction) -> State {
+            match i.direction {
+                Direction::Forward => State {
+                    position: current.position + i.amount,
+                    depth: current.depth + i.amount * current.aim,
+                    ..current
+                },
+                Direction::Down => State {
+                    aim: current.aim + i.amount,
+                    ..current
+                },
+                Direction::Up => State {
+                    aim: current.aim - i.amount,
+                    ..current
+                },
+            }
+        }
+    }
+
+    /// Every state of the submarine, starting with the initial one before any instruction.
+    pub struct Voyage<'a> {
+        instructions: &'a [Instruction],
+        states: Vec<State>,
+    }
+
+    impl<'a> Voyage<'a> {
+        pub fn replay(semantics: &impl Semantics, instructions: &'a [Instruction]) -> Voyage<'a> {
+            let mut states = vec![State::default()];
+            for i in instructions {
+                states.push(semantics.apply(*states.last().unwrap(), i));
+            }
+            Voyage {
+                instructions,
+                states,
+            }
+        }
+
+        pub fn last(&self) -> State {
+            *self.states.last().unwrap()
+        }
+
+        pub fn max_depth(&self) -> i32 {
+            self.states
+                .iter()
+                .map(|s| s.depth)
+                .max()
+                .unwrap_or_default()
+        }
+
+        /// Number of instructions after which the depth first exceeds the threshold.
+        pub fn first_deeper_than(&self, threshold: i32) -> Option<usize> {
+            self.states.iter().position(|s| s.depth > threshold)
+        }
+
+        pub fn to_csv(&self) -> String {
+            let mut result = String::from("step,direction,amount,position,depth,aim\n");
+            for (step, state) in self.states.iter().enumerate() {
+                let (direction, amount) = match step.checked_sub(1) {
+                    Some(i) => (
+                        self.instructions[i].direction.to_string(),
+                        self.instructions[i].amount.to_string(),
+                    ),
+                    None => (String::new(), String::new()),
+                };
+                result.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    step, direction, amount, state.position, state.depth, state.aim
+                ));
+            }
+            result
+        }
     }
 }
 
+use submarine::{Aimed, Plain, Voyage};
+
 fn main() {
     let instructions = aoc::get_input(21, 2)
         .split('\n')
@@ -63,7 +154,36 @@ fn main() {
         .filter_map(Result::ok)
         .collect::<Vec<_>>();
 
-    let end_pos = instructions.iter().fold(AimPosition::default(), follow);
-    println!("Part 1: {}", end_pos.aim * end_pos.position);
-    println!("Part 2: {}", end_pos.depth * end_pos.position);
+    let plain = Voyage::replay(&Plain, &instructions);
+    let aimed = Voyage::replay(&Aimed, &instructions);
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["--csv", "plain"] => print!("{}", plain.to_csv()),
+        ["--csv", "aimed"] => print!("{}", aimed.to_csv()),
+        ["--deeper-than", threshold] => {
+            let threshold = threshold.parse().expect("Invalid threshold");
+            for (name, voyage) in [("plain", &plain), ("aimed", &aimed)] {
+                println!(
+                    "{}: max depth {}, first deeper than {} after step {:?}",
+                    name,
+                    voyage.max_depth(),
+                    threshold,
+                    voyage.first_deeper_than(threshold)
+                );
+            }
+        }
+        [] => {
+            let end_plain = plain.last();
+            let end_aimed = aimed.last();
+            println!("Part 1: {}", end_plain.depth * end_plain.position);
+            println!("Part 2: {}", end_aimed.depth * end_aimed.position);
+        }
+        _ => panic!("Usage: 02 [--csv plain|aimed | --deeper-than DEPTH]"),
+    }
 }
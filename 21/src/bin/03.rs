@@ -1,73 +1,112 @@
-use partition::partition;
+use std::str::FromStr;
 
-fn add_ones(acc: Vec<i32>, v: &String) -> Vec<i32> {
-    acc.into_iter()
-        .zip(v.chars())
-        .map(|(count, ch)| if ch == '1' { count + 1 } else { count })
-        .collect()
-}
+mod diagnostics {
+    use std::str::FromStr;
 
-fn power_consumption(ns: &Vec<String>) -> i32 {
-    let bit_size = ns[0].len();
-    let n_ones = ns.iter().fold(vec![0; bit_size], add_ones);
-    let n_numbers = ns.len();
-    let (gamma_rate, epsilon_rate) = n_ones.into_iter().fold((0, 0), |(gamma, epsilon), count| {
-        if count as usize > n_numbers / 2 {
-            ((gamma << 1) + 1, epsilon << 1)
-        } else {
-            (gamma << 1, (epsilon << 1) + 1)
-        }
-    });
-    gamma_rate * epsilon_rate
-}
+    use anyhow::{bail, Context};
+    use num_bigint::BigUint;
 
-#[derive(PartialEq, Eq)]
-enum Criteria {
-    LeastCommon,
-    MostCommon,
-}
+    const MAX_WIDTH: usize = 128;
 
-fn extract_extreme(mut ns: &mut [String], criteria: Criteria) -> i32 {
-    let mut bit = 1;
-    while ns.len() > 1 {
-        let (longer, shorter) = partition_numbers_by_bit(ns, bit);
-        ns = if criteria == Criteria::MostCommon {
-            longer
-        } else {
-            shorter
-        };
-        bit = bit + 1;
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Criteria {
+        LeastCommon,
+        MostCommon,
     }
-    binary_to_i32(&ns[0])
-}
 
-fn binary_to_i32(s: &str) -> i32 {
-    s.chars().fold(0, |acc, c| (acc << 1) + (c == '1') as i32)
-}
+    /// Diagnostic numbers of a fixed bit width, kept sorted.
+    #[derive(Debug)]
+    pub struct Report {
+        width: usize,
+        numbers: Vec<u128>,
+    }
 
-fn partition_numbers_by_bit(ns: &mut [String], bit: usize) -> (&mut [String], &mut [String]) {
-    let (zeroes, ones) = partition(ns, |s| s.chars().nth(bit).unwrap() == '0');
-    if zeroes.len() > ones.len() {
-        (zeroes, ones)
-    } else {
-        (ones, zeroes)
+    impl FromStr for Report {
+        type Err = anyhow::Error;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let lines = s.lines().filter(|l| !l.is_empty()).collect::<Vec<_>>();
+            let width = lines.first().context("No numbers supplied")?.len();
+            if width == 0 || width > MAX_WIDTH {
+                bail!("Width of {} bits is not supported", width);
+            }
+            let mut numbers = lines
+                .iter()
+                .map(|l| {
+                    if l.len() != width {
+                        bail!("Not all numbers of the same width");
+                    }
+                    if l.bytes().any(|b| b != b'0' && b != b'1') {
+                        bail!("'{}' is not a binary number", l);
+                    }
+                    Ok(u128::from_str_radix(l, 2)?)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            numbers.sort_unstable();
+            Ok(Report { width, numbers })
+        }
     }
-}
 
-fn life_support_rating(ns: &mut Vec<String>) -> i32 {
-    let (oxygen_range, co2_range) = partition_numbers_by_bit(ns, 0);
-    let oxygen = extract_extreme(oxygen_range, Criteria::MostCommon);
-    let co2 = extract_extreme(co2_range, Criteria::LeastCommon);
-    oxygen * co2
+    impl Report {
+        fn mask(&self) -> u128 {
+            u128::MAX >> (MAX_WIDTH - self.width)
+        }
+
+        fn ones_in(numbers: &[u128], bit: usize) -> usize {
+            numbers.iter().filter(|&&n| n >> bit & 1 == 1).count()
+        }
+
+        /// Bits that are set in more than half of the numbers.
+        pub fn gamma(&self) -> u128 {
+            (0..self.width)
+                .filter(|&bit| 2 * Report::ones_in(&self.numbers, bit) > self.numbers.len())
+                .fold(0, |acc, bit| acc | 1 << bit)
+        }
+
+        pub fn epsilon(&self) -> u128 {
+            !self.gamma() & self.mask()
+        }
+
+        pub fn power_consumption(&self) -> BigUint {
+            BigUint::from(self.gamma()) * self.epsilon()
+        }
+
+        // Numbers sharing all bits above `bit` are contiguous in the sorted array, and within
+        // such a range those with the bit unset come first.
+        pub fn extreme(&self, criteria: Criteria) -> Option<u128> {
+            let mut range = &self.numbers[..];
+            for bit in (0..self.width).rev() {
+                if range.len() <= 1 {
+                    break;
+                }
+                let split = range.partition_point(|&n| n >> bit & 1 == 0);
+                let (zeroes, ones) = range.split_at(split);
+                range = match criteria {
+                    Criteria::MostCommon if ones.len() >= zeroes.len() => ones,
+                    Criteria::MostCommon => zeroes,
+                    Criteria::LeastCommon if zeroes.is_empty() || ones.is_empty() => range,
+                    Criteria::LeastCommon if zeroes.len() <= ones.len() => zeroes,
+                    Criteria::LeastCommon => ones,
+                };
+            }
+            range.first().copied()
+        }
+
+        pub fn life_support_rating(&self) -> Option<BigUint> {
+            let oxygen = self.extreme(Criteria::MostCommon)?;
+            let co2 = self.extreme(Criteria::LeastCommon)?;
+            Some(BigUint::from(oxygen) * co2)
+        }
+    }
 }
 
+use diagnostics::Report;
+
 fn main() {
-    let mut input = aoc::get_input(21, 3)
-        .split('\n')
-        .filter(|v| !v.is_empty())
-        .map(str::to_owned)
-        .collect::<Vec<_>>();
-
-    println!("Part 1: {}", power_consumption(&input));
-    println!("Part 2: {}", life_support_rating(&mut input));
+    let report = Report::from_str(&aoc::get_input(21, 3)).expect("Invalid input");
+
+    println!("Part 1: {}", report.power_consumption());
+    println!(
+        "Part 2: {}",
+        report.life_support_rating().expect("No numbers supplied")
+    );
 }
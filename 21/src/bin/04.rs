@@ -1,120 +1,163 @@
 use anyhow::{bail, Result};
 
 mod bingo {
+    use std::collections::HashMap;
+
+    use anyhow::{bail, Result};
+
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Rules {
+        pub diagonals: bool,
+    }
+
     #[derive(Debug)]
-    pub struct BingoBoard([[i8; 5]; 5], [[bool; 5]; 5]);
+    pub struct BingoBoard {
+        size: usize,
+        numbers: Vec<i64>,
+        marked: Vec<bool>,
+        cells: HashMap<i64, usize>,
+        row_marks: Vec<usize>,
+        column_marks: Vec<usize>,
+        diagonal_marks: [usize; 2],
+    }
 
     impl BingoBoard {
-        pub fn new(numbers: [[i8; 5]; 5]) -> BingoBoard {
-            BingoBoard(numbers, [[false; 5]; 5])
-        }
-
-        fn all_checked_row(&self, row: usize) -> bool {
-            for column in 0usize..5 {
-                if !self.1[row][column] {
-                    return false;
+        pub fn new(size: usize, numbers: Vec<i64>) -> Result<BingoBoard> {
+            if size == 0 || numbers.len() != size * size {
+                bail!("wrong number of cells for a {}x{} board", size, size);
+            }
+            let mut cells = HashMap::new();
+            for (i, &n) in numbers.iter().enumerate() {
+                if cells.insert(n, i).is_some() {
+                    bail!("number {} appears twice on the board", n);
                 }
             }
-            true
+            Ok(BingoBoard {
+                size,
+                marked: vec![false; numbers.len()],
+                numbers,
+                cells,
+                row_marks: vec![0; size],
+                column_marks: vec![0; size],
+                diagonal_marks: [0; 2],
+            })
         }
 
-        fn all_checked_column(&self, column: usize) -> bool {
-            for row in 0usize..5 {
-                if !self.1[row][column] {
-                    return false;
-                }
+        pub fn mark_and_check(&mut self, number: i64, rules: Rules) -> bool {
+            let i = match self.cells.get(&number) {
+                Some(&i) if !self.marked[i] => i,
+                _ => return false,
+            };
+            self.marked[i] = true;
+            let (row, column) = (i / self.size, i % self.size);
+            self.row_marks[row] += 1;
+            self.column_marks[column] += 1;
+            let mut won =
+                self.row_marks[row] == self.size || self.column_marks[column] == self.size;
+            if row == column {
+                self.diagonal_marks[0] += 1;
+                won |= rules.diagonals && self.diagonal_marks[0] == self.size;
+            }
+            if row + column + 1 == self.size {
+                self.diagonal_marks[1] += 1;
+                won |= rules.diagonals && self.diagonal_marks[1] == self.size;
             }
-            true
+            won
         }
 
-        pub fn mark_and_check(&mut self, number: i8) -> bool {
-            for row in 0usize..5 {
-                for column in 0usize..5 {
-                    if self.0[row][column] == number {
-                        self.1[row][column] = true;
-                        return self.all_checked_row(row) || self.all_checked_column(column);
-                    }
-                }
-            }
-            false
+        pub fn unchecked(&self) -> impl Iterator<Item = i64> + '_ {
+            self.numbers
+                .iter()
+                .zip(self.marked.iter())
+                .filter(|(_, &m)| !m)
+                .map(|(&n, _)| n)
         }
+    }
 
-        pub fn unchecked(&self) -> Vec<i8> {
-            let mut result = vec![];
-            for row in 0usize..5 {
-                for column in 0usize..5 {
-                    if !self.1[row][column] {
-                        result.push(self.0[row][column]);
-                    }
+    #[derive(Debug, Clone, Copy)]
+    pub struct Win {
+        pub board: usize,
+        pub draw: usize,
+        pub number: i64,
+        pub score: i64,
+    }
+
+    /// Every board that wins, in order of winning. Boards stop playing once they won.
+    pub fn play(boards: &mut [BingoBoard], draws: &[i64], rules: Rules) -> Vec<Win> {
+        let mut result = vec![];
+        let mut won = vec![false; boards.len()];
+        for (draw, &number) in draws.iter().enumerate() {
+            for (board, b) in boards.iter_mut().enumerate() {
+                if !won[board] && b.mark_and_check(number, rules) {
+                    won[board] = true;
+                    result.push(Win {
+                        board,
+                        draw,
+                        number,
+                        score: b.unchecked().sum::<i64>() * number,
+                    });
                 }
             }
-            result
         }
+        result
     }
 }
 
-use bingo::BingoBoard;
+use bingo::{play, BingoBoard, Rules};
 
-impl TryFrom<&[String]> for BingoBoard {
+impl TryFrom<&[&str]> for BingoBoard {
     type Error = anyhow::Error;
-    fn try_from(value: &[String]) -> Result<Self, Self::Error> {
-        if value.len() != 5 {
-            bail!("wrong size of input array");
-        }
-        let mut bingo = [[0i8; 5]; 5];
-        for (row, a) in value.iter().enumerate() {
-            let nums = a
-                .split(' ')
-                .filter_map(|a| a.parse::<i8>().ok())
-                .collect::<Vec<_>>();
-            if nums.len() != 5 {
+    fn try_from(value: &[&str]) -> Result<Self, Self::Error> {
+        let size = value.len();
+        let mut numbers = vec![];
+        for row in value {
+            let nums = row
+                .split_whitespace()
+                .map(|a| a.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()?;
+            if nums.len() != size {
                 bail!("Wrong size of line");
             }
-            for (col, &v) in nums.iter().enumerate() {
-                bingo[row][col] = v;
-            }
+            numbers.extend(nums);
         }
-        Ok(BingoBoard::new(bingo))
+        BingoBoard::new(size, numbers)
     }
 }
 
-fn bingo_on(board: &mut BingoBoard, draws: &[i8]) -> Result<(usize, i32)> {
-    for (n, &v) in draws.iter().enumerate() {
-        if board.mark_and_check(v) {
-            return Ok((n, score(board, v)));
+fn main() {
+    let mut rules = Rules::default();
+    let mut replay = false;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--diagonals" => rules.diagonals = true,
+            "--replay" => replay = true,
+            _ => panic!("Unknown argument {}", arg),
         }
     }
-    bail!("the board never wins")
-}
 
-fn score(board: &BingoBoard, last_drawn: i8) -> i32 {
-    board.unchecked().into_iter().map(i32::from).sum::<i32>() * (last_drawn as i32)
-}
-
-fn main() {
-    let input = aoc::get_input(21, 4)
-        .split('\n')
-        .map(str::to_owned)
-        .collect::<Vec<_>>();
-    let draws = input[0]
+    let input = aoc::get_input(21, 4);
+    let lines = input.lines().collect::<Vec<_>>();
+    let draws = lines[0]
         .split(',')
-        .filter_map(|s| s.parse::<i8>().ok())
-        .collect::<Vec<_>>();
-    let mut bingos = vec![];
-    let mut begin_id = 2usize;
-    while begin_id < input.len() {
-        if let Ok(bingo) = BingoBoard::try_from(&input[begin_id..begin_id + 5]) {
-            bingos.push(bingo);
+        .map(|s| s.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Invalid draws");
+    let mut boards = lines[1..]
+        .split(|l| l.trim().is_empty())
+        .filter(|b| !b.is_empty())
+        .map(BingoBoard::try_from)
+        .collect::<Result<Vec<_>>>()
+        .expect("Invalid board");
+
+    let wins = play(&mut boards, &draws, rules);
+    if replay {
+        for win in &wins {
+            println!(
+                "Board {} wins on draw {} ({}) with score {}",
+                win.board, win.draw, win.number, win.score
+            );
         }
-        begin_id += 6;
     }
-
-    let mut bingos = bingos
-        .iter_mut()
-        .filter_map(|b| bingo_on(b, &draws).ok())
-        .collect::<Vec<_>>();
-    bingos.sort_by(|(on_left, _), (on_right, _)| on_left.cmp(on_right));
-
-    println!("Part 1: {}", bingos[0].1);
-    println!("Part 2: {}", bingos[bingos.len() - 1].1);
+    println!("Part 1: {}", wins.first().expect("No board wins").score);
+    println!("Part 2: {}", wins.last().expect("No board wins").score);
 }
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 use std::ops::{Add, Sub};
 use std::str::FromStr;
+use std::time::Instant;
 
 use anyhow::{bail, Context};
 use num_integer::gcd;
@@ -33,7 +34,7 @@ impl Sub<Point> for Point {
 
 impl Point {
     fn normalize(&self) -> Point {
-        let gcd = gcd(self.x, self.y);
+        let gcd = gcd(self.x, self.y).max(1);
         Point {
             x: self.x / gcd,
             y: self.y / gcd,
@@ -54,7 +55,7 @@ impl FromStr for Point {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 struct Line(Point, Point);
 
 struct PointIterator {
@@ -68,7 +69,7 @@ impl PointIterator {
     fn new(begin: Point, end: Point) -> PointIterator {
         PointIterator {
             current: begin,
-            end: end,
+            end,
             step: (end - begin).normalize(),
             spent: false,
         }
@@ -101,7 +102,7 @@ impl Line {
     }
 
     fn follow(&self) -> impl Iterator<Item = Point> {
-        return PointIterator::new(self.0, self.1);
+        PointIterator::new(self.0, self.1)
     }
 }
 
@@ -116,6 +117,193 @@ impl FromStr for Line {
     }
 }
 
+mod segments {
+    use std::collections::{HashMap, HashSet};
+
+    use num_integer::gcd;
+
+    use super::{Line, Point};
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Intersection {
+        Disjoint,
+        /// Segments meet in a single point, `None` when it is not a lattice point.
+        Crossing(Option<Point>),
+        /// Collinear segments sharing this part.
+        Overlap(Line),
+    }
+
+    type Vector = (i64, i64);
+    // Part of a line as positions along it (p . direction) and the matching points
+    type Range = (i64, Point, i64, Point);
+
+    fn vector(p: Point) -> Vector {
+        (p.x as i64, p.y as i64)
+    }
+
+    fn sub(a: Vector, b: Vector) -> Vector {
+        (a.0 - b.0, a.1 - b.1)
+    }
+
+    fn dot(a: Vector, b: Vector) -> i64 {
+        a.0 * b.0 + a.1 * b.1
+    }
+
+    fn cross(a: Vector, b: Vector) -> i64 {
+        a.0 * b.1 - a.1 * b.0
+    }
+
+    fn contains(line: &Line, p: Point) -> bool {
+        let (a, b, p) = (vector(line.0), vector(line.1), vector(p));
+        if a == b {
+            return p == a;
+        }
+        cross(sub(b, a), sub(p, a)) == 0
+            && dot(sub(p, a), sub(b, a)) >= 0
+            && dot(sub(p, b), sub(a, b)) >= 0
+    }
+
+    /// Number of lattice points on the segment, ends included.
+    pub fn lattice_points(line: &Line) -> i64 {
+        let (dx, dy) = sub(vector(line.1), vector(line.0));
+        gcd(dx, dy) + 1
+    }
+
+    pub fn intersect(a: &Line, b: &Line) -> Intersection {
+        let (p, q) = (vector(a.0), vector(b.0));
+        let (r, s) = (sub(vector(a.1), p), sub(vector(b.1), q));
+        if r == (0, 0) || s == (0, 0) {
+            let (point, other) = if r == (0, 0) { (a.0, b) } else { (b.0, a) };
+            return if contains(other, point) {
+                Intersection::Crossing(Some(point))
+            } else {
+                Intersection::Disjoint
+            };
+        }
+
+        let qp = sub(q, p);
+        let denominator = cross(r, s);
+        if denominator == 0 {
+            if cross(qp, r) != 0 {
+                return Intersection::Disjoint;
+            }
+            // Collinear, compare the projections of all ends on the first segment
+            let projection = |x: Point| dot(sub(vector(x), p), r);
+            let (t0, t1) = (projection(b.0), projection(b.1));
+            let low = t0.min(t1).max(0);
+            let high = t0.max(t1).min(dot(r, r));
+            if low > high {
+                return Intersection::Disjoint;
+            }
+            let ends = [a.0, a.1, b.0, b.1];
+            let start = *ends.iter().find(|&&e| projection(e) == low).unwrap();
+            let end = *ends.iter().find(|&&e| projection(e) == high).unwrap();
+            return Intersection::Overlap(Line(start, end));
+        }
+
+        let (t, u, denominator) = if denominator > 0 {
+            (cross(qp, s), cross(qp, r), denominator)
+        } else {
+            (-cross(qp, s), -cross(qp, r), -denominator)
+        };
+        if t < 0 || t > denominator || u < 0 || u > denominator {
+            return Intersection::Disjoint;
+        }
+        let (x, y) = (r.0 * t, r.1 * t);
+        if x % denominator != 0 || y % denominator != 0 {
+            return Intersection::Crossing(None);
+        }
+        Intersection::Crossing(Some(Point {
+            x: (p.0 + x / denominator) as i32,
+            y: (p.1 + y / denominator) as i32,
+        }))
+    }
+
+    // Smallest lattice step along the line, pointing right or up
+    fn direction(line: &Line) -> Vector {
+        let (dx, dy) = sub(vector(line.1), vector(line.0));
+        let g = gcd(dx, dy);
+        let (dx, dy) = (dx / g, dy / g);
+        if dx < 0 || (dx == 0 && dy < 0) {
+            (-dx, -dy)
+        } else {
+            (dx, dy)
+        }
+    }
+
+    /// Lattice points covered by at least two segments, only intersections of segment pairs are
+    /// computed, never the points along the segments.
+    pub fn count_covered_twice(lines: &[&Line]) -> usize {
+        let mut crossings = vec![];
+        // Collinear overlaps grouped by line
+        let mut overlaps: HashMap<(Vector, i64), Vec<Range>> = HashMap::new();
+        for (i, a) in lines.iter().enumerate() {
+            for b in &lines[i + 1..] {
+                match intersect(a, b) {
+                    Intersection::Crossing(Some(p)) => crossings.push(p),
+                    Intersection::Overlap(l) if l.0 == l.1 => crossings.push(l.0),
+                    Intersection::Overlap(l) => {
+                        let d = direction(&l);
+                        let (s0, s1) = (dot(vector(l.0), d), dot(vector(l.1), d));
+                        let range = if s0 < s1 {
+                            (s0, l.0, s1, l.1)
+                        } else {
+                            (s1, l.1, s0, l.0)
+                        };
+                        overlaps
+                            .entry((d, cross(d, vector(l.0))))
+                            .or_default()
+                            .push(range);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Disjoint parts covered by overlaps, parts on the same line are merged
+        let mut covered = vec![];
+        for ranges in overlaps.values_mut() {
+            ranges.sort_unstable_by_key(|r| (r.0, r.2));
+            let mut merged: Vec<Range> = vec![];
+            for &range in ranges.iter() {
+                match merged.last_mut() {
+                    Some(last) if range.0 <= last.2 => {
+                        if range.2 > last.2 {
+                            (last.2, last.3) = (range.2, range.3);
+                        }
+                    }
+                    _ => merged.push(range),
+                }
+            }
+            covered.extend(
+                merged
+                    .into_iter()
+                    .map(|(_, start, _, end)| Line(start, end)),
+            );
+        }
+        let mut result = covered.iter().map(lattice_points).sum::<i64>() as usize;
+
+        // Parts on different lines can still share a point, which should be counted once
+        let mut shared: HashMap<Point, HashSet<usize>> = HashMap::new();
+        for (i, a) in covered.iter().enumerate() {
+            for (j, b) in covered.iter().enumerate().skip(i + 1) {
+                if let Intersection::Crossing(Some(p)) = intersect(a, b) {
+                    shared.entry(p).or_default().extend([i, j]);
+                }
+            }
+        }
+        result -= shared.values().map(|parts| parts.len() - 1).sum::<usize>();
+
+        crossings.sort_unstable_by_key(|p| (p.x, p.y));
+        crossings.dedup();
+        result
+            + crossings
+                .into_iter()
+                .filter(|&p| !covered.iter().any(|l| contains(l, p)))
+                .count()
+    }
+}
+
 fn count_crossings<'a>(lines: impl Iterator<Item = &'a Line>) -> usize {
     let mut visited = HashSet::new();
     let mut duplicated = HashSet::new();
@@ -132,19 +320,143 @@ fn count_crossings<'a>(lines: impl Iterator<Item = &'a Line>) -> usize {
     duplicated.len()
 }
 
+// Overlap counts on a dense grid covering the bounding box of the lines
+struct DensityMap {
+    origin: Point,
+    width: usize,
+    height: usize,
+    counts: Vec<u32>,
+}
+
+impl DensityMap {
+    fn new<'a>(lines: impl Iterator<Item = &'a Line> + Clone) -> DensityMap {
+        let points = lines.clone().flat_map(|l| [l.0, l.1]);
+        let min_x = points.clone().map(|p| p.x).min().unwrap_or(0);
+        let max_x = points.clone().map(|p| p.x).max().unwrap_or(-1);
+        let min_y = points.clone().map(|p| p.y).min().unwrap_or(0);
+        let max_y = points.map(|p| p.y).max().unwrap_or(-1);
+        let width = (max_x - min_x + 1) as usize;
+        let height = (max_y - min_y + 1) as usize;
+        let mut result = DensityMap {
+            origin: Point { x: min_x, y: min_y },
+            width,
+            height,
+            counts: vec![0; width * height],
+        };
+        for line in lines {
+            for point in line.follow() {
+                let offset = point - result.origin;
+                result.counts[offset.y as usize * width + offset.x as usize] += 1;
+            }
+        }
+        result
+    }
+
+    fn overlaps(&self) -> usize {
+        self.counts.iter().filter(|&&c| c >= 2).count()
+    }
+
+    // Plain PGM, brighter cells are covered by more lines
+    fn to_pgm(&self) -> String {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut result = format!("P2\n{} {}\n{}\n", self.width, self.height, max);
+        for row in self.counts.chunks(self.width.max(1)) {
+            let values = row.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+            result.push_str(&values.join(" "));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+fn bench(lines: &[Line]) {
+    const REPETITIONS: u32 = 10;
+    let start = Instant::now();
+    let mut sparse = 0;
+    for _ in 0..REPETITIONS {
+        sparse = count_crossings(lines.iter());
+    }
+    let sparse_time = start.elapsed() / REPETITIONS;
+    let start = Instant::now();
+    let mut dense = 0;
+    for _ in 0..REPETITIONS {
+        dense = DensityMap::new(lines.iter()).overlaps();
+    }
+    let dense_time = start.elapsed() / REPETITIONS;
+    let start = Instant::now();
+    let exact = segments::count_covered_twice(&lines.iter().collect::<Vec<_>>());
+    let exact_time = start.elapsed();
+    println!("Hash set: {} overlaps in {:?}", sparse, sparse_time);
+    println!("Dense grid: {} overlaps in {:?}", dense, dense_time);
+    println!(
+        "Segment intersections: {} overlaps in {:?}",
+        exact, exact_time
+    );
+}
+
 fn main() {
     let lines = aoc::get_input(21, 5)
         .split('\n')
         .filter_map(|x| Line::from_str(x).ok())
         .collect::<Vec<_>>();
 
-    println!(
-        "Part 1: {}",
-        count_crossings(
-            lines
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("--bench") => bench(&lines),
+        Some("--pgm") => {
+            let path = args.next().expect("Missing output path");
+            std::fs::write(path, DensityMap::new(lines.iter()).to_pgm())
+                .expect("Could not write image");
+        }
+        Some(arg) => panic!("Unknown argument {}", arg),
+        None => {
+            let straight = lines
                 .iter()
                 .filter(|x| x.is_horizontal() || x.is_vertical())
-        )
-    );
-    println!("Part 2: {}", count_crossings(lines.iter()));
+                .collect::<Vec<_>>();
+            let all = lines.iter().collect::<Vec<_>>();
+            println!(
+                "Part 1: {}",
+                DensityMap::new(straight.iter().copied()).overlaps()
+            );
+            println!(
+                "Part 2: {}",
+                DensityMap::new(all.iter().copied()).overlaps()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::segments::{count_covered_twice, intersect, Intersection};
+    use super::*;
+
+    #[test]
+    fn single_points_meet_only_when_equal() {
+        let (p, q) = (Point { x: 1, y: 2 }, Point { x: 3, y: 4 });
+        assert_eq!(intersect(&Line(p, p), &Line(q, q)), Intersection::Disjoint);
+        assert_eq!(
+            intersect(&Line(p, p), &Line(p, p)),
+            Intersection::Crossing(Some(p))
+        );
+    }
+
+    #[test]
+    fn segments_agree_with_rasterizer() {
+        let mut rng = aoc::XorShift::new(5);
+        let mut point = || Point {
+            x: rng.below(12) as i32,
+            y: rng.below(12) as i32,
+        };
+        for _ in 0..2000 {
+            let lines = (0..6).map(|_| Line(point(), point())).collect::<Vec<_>>();
+            assert_eq!(
+                count_covered_twice(&lines.iter().collect::<Vec<_>>()),
+                DensityMap::new(lines.iter()).overlaps(),
+                "{:?}",
+                lines
+            );
+        }
+    }
 }
@@ -1,37 +1,117 @@
-fn n_jellyfish(v: i32, t: i32, cache: &mut [Option<usize>]) -> usize {
-    if v >= t {
-        1
-    } else if v != 0 {
-        n_jellyfish(0, t - v, cache)
-    } else if let Some(result) = cache[t as usize] {
-        result
-    } else {
-        let result = n_jellyfish(6, t - 1, cache) + n_jellyfish(8, t - 1, cache);
-        cache[t as usize] = Some(result);
+use num_bigint::BigUint;
+
+mod population {
+    use num_bigint::BigUint;
+
+    const N_TIMERS: usize = 9;
+    const RESET_TIMER: usize = 6;
+
+    type Matrix = Vec<Vec<BigUint>>;
+
+    fn identity() -> Matrix {
+        (0..N_TIMERS)
+            .map(|r| {
+                (0..N_TIMERS)
+                    .map(|c| BigUint::from((r == c) as u32))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Fish with timer 0 become a fish with timer 6 and a new one with timer 8, the rest count down
+    fn transition() -> Matrix {
+        let mut result = vec![vec![BigUint::default(); N_TIMERS]; N_TIMERS];
+        for timer in 1..N_TIMERS {
+            result[timer - 1][timer] = BigUint::from(1u32);
+        }
+        result[RESET_TIMER][0] = BigUint::from(1u32);
+        result[N_TIMERS - 1][0] = BigUint::from(1u32);
         result
     }
+
+    fn multiply(a: &Matrix, b: &Matrix, modulus: Option<&BigUint>) -> Matrix {
+        (0..N_TIMERS)
+            .map(|r| {
+                (0..N_TIMERS)
+                    .map(|c| {
+                        let v = (0..N_TIMERS).map(|k| &a[r][k] * &b[k][c]).sum::<BigUint>();
+                        match modulus {
+                            Some(m) => v % m,
+                            None => v,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Fish grouped by their timer.
+    pub struct Population {
+        counts: Vec<BigUint>,
+    }
+
+    impl Population {
+        pub fn new(timers: &[usize]) -> Option<Population> {
+            let mut counts = vec![BigUint::default(); N_TIMERS];
+            for &t in timers {
+                *counts.get_mut(t)? += 1u32;
+            }
+            Some(Population { counts })
+        }
+
+        /// Number of fish after `days`, reduced by `modulus` when given.
+        pub fn size_after(&self, days: u64, modulus: Option<&BigUint>) -> BigUint {
+            let mut power = identity();
+            let mut base = transition();
+            let mut remaining = days;
+            while remaining > 0 {
+                if remaining & 1 == 1 {
+                    power = multiply(&power, &base, modulus);
+                }
+                base = multiply(&base, &base, modulus);
+                remaining >>= 1;
+            }
+            let total = power
+                .iter()
+                .flat_map(|row| row.iter().zip(self.counts.iter()).map(|(p, c)| p * c))
+                .sum::<BigUint>();
+            match modulus {
+                Some(m) => total % m,
+                None => total,
+            }
+        }
+    }
 }
 
+use population::Population;
+
 fn main() {
     let jellyfish = aoc::get_input(21, 6)
         .trim()
         .split(',')
-        .filter_map(|c| c.parse::<i32>().ok())
+        .filter_map(|c| c.parse::<usize>().ok())
         .collect::<Vec<_>>();
+    let population = Population::new(&jellyfish).expect("Timer out of range");
 
-    let mut cache = [None; 265];
-    println!(
-        "Part 1: {}",
-        jellyfish
-            .iter()
-            .map(|&j| n_jellyfish(j, 80, &mut cache))
-            .sum::<usize>()
-    );
-    println!(
-        "Part 2: {}",
-        jellyfish
-            .iter()
-            .map(|&j| n_jellyfish(j, 256, &mut cache))
-            .sum::<usize>()
-    );
+    let mut args = std::env::args().skip(1);
+    match args.next() {
+        Some(days) => {
+            let days = days.parse().expect("Invalid number of days");
+            let modulus = args
+                .next()
+                .map(|m| m.parse::<BigUint>().expect("Invalid modulus"));
+            if modulus.as_ref().is_some_and(|m| *m == BigUint::default()) {
+                panic!("Modulus has to be positive");
+            }
+            println!(
+                "After {} days: {}",
+                days,
+                population.size_after(days, modulus.as_ref())
+            );
+        }
+        None => {
+            println!("Part 1: {}", population.size_after(80, None));
+            println!("Part 2: {}", population.size_after(256, None));
+        }
+    }
 }
@@ -1,29 +1,170 @@
-fn sum_ints_until(n: i32) -> i32 {
-    (n * (n + 1)) / 2
+mod alignment {
+    use anyhow::Context;
+
+    /// Fuel a crab spends to move a given distance.
+    pub enum Cost {
+        Linear,
+        Triangular,
+        /// Any cost that is convex and non-decreasing in the distance, so that the total fuel is
+        /// convex in the position.
+        Convex(Box<dyn Fn(i64) -> Option<i64>>),
+    }
+
+    impl Cost {
+        /// `None` when the fuel overflows an i64.
+        pub fn of(&self, distance: i64) -> Option<i64> {
+            match self {
+                Cost::Linear => Some(distance),
+                Cost::Triangular => distance.checked_mul(distance + 1).map(|d| d / 2),
+                Cost::Convex(f) => f(distance),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Alignment {
+        pub position: i64,
+        pub fuel: i64,
+    }
+
+    pub fn fuel(crabs: &[i64], cost: &Cost, position: i64) -> Option<i64> {
+        crabs.iter().try_fold(0i64, |acc, c| {
+            acc.checked_add(cost.of((c - position).abs())?)
+        })
+    }
+
+    // `None` when the fuel for one of the positions overflows
+    fn best_of(
+        crabs: &[i64],
+        cost: &Cost,
+        positions: impl Iterator<Item = i64>,
+    ) -> Option<Alignment> {
+        positions
+            .map(|position| fuel(crabs, cost, position).map(|fuel| Alignment { position, fuel }))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min_by_key(|a| (a.fuel, a.position))
+    }
+
+    #[cfg(test)]
+    pub fn brute_force(crabs: &[i64], cost: &Cost) -> anyhow::Result<Alignment> {
+        let min = *crabs.iter().min().context("No crabs provided")?;
+        let max = *crabs.iter().max().unwrap();
+        best_of(crabs, cost, min..=max).context("Fuel overflows")
+    }
+
+    pub fn align(crabs: &[i64], cost: &Cost) -> anyhow::Result<Alignment> {
+        let min = *crabs.iter().min().context("No crabs provided")?;
+        let max = *crabs.iter().max().unwrap();
+        let best = match cost {
+            Cost::Linear => {
+                let mut sorted = crabs.to_owned();
+                sorted.sort_unstable();
+                let median = sorted[(sorted.len() - 1) / 2];
+                best_of(crabs, cost, median..=median)
+            }
+            // The optimum lies within half a step of the mean
+            Cost::Triangular => {
+                let sum = crabs.iter().map(|&c| c as i128).sum::<i128>();
+                let mean = sum.div_euclid(crabs.len() as i128) as i64;
+                best_of(crabs, cost, (mean - 1).max(min)..=(mean + 1).min(max))
+            }
+            Cost::Convex(_) => {
+                let (mut low, mut high) = (min, max);
+                while high - low > 2 {
+                    let m1 = low + (high - low) / 3;
+                    let m2 = high - (high - low) / 3;
+                    let (f1, f2) = fuel(crabs, cost, m1)
+                        .zip(fuel(crabs, cost, m2))
+                        .context("Fuel overflows")?;
+                    if f1 <= f2 {
+                        high = m2;
+                    } else {
+                        low = m1;
+                    }
+                }
+                best_of(crabs, cost, low..=high)
+            }
+        };
+        best.context("Fuel overflows")
+    }
 }
 
+use alignment::{align, Cost};
+
 fn main() {
     let crab_positions = aoc::get_input(21, 7)
         .trim()
         .split(',')
-        .filter_map(|s| s.parse::<i32>().ok())
+        .filter_map(|s| s.parse::<i64>().ok())
         .collect::<Vec<_>>();
-    let linear = crab_positions
-        .iter()
-        .map(|p| crab_positions.iter().map(|x| (x - p).abs()).sum::<i32>())
-        .min()
-        .unwrap();
-    println!("Part 1: {}", linear);
-
-    let quadratic = crab_positions
-        .iter()
-        .map(|p| {
-            crab_positions
-                .iter()
-                .map(|x| sum_ints_until((x - p).abs()))
-                .sum::<i32>()
-        })
-        .min()
-        .unwrap();
-    println!("Part 2: {}", quadratic);
+
+    if let Some(power) = std::env::args().nth(1) {
+        let power = power
+            .parse::<u32>()
+            .ok()
+            .filter(|&p| p >= 1)
+            .expect("Usage: 07 [POWER]");
+        let cost = Cost::Convex(Box::new(move |d: i64| d.checked_pow(power)));
+        let best = align(&crab_positions, &cost).expect("Cannot align crabs");
+        println!(
+            "Distance to the power {}: {} (position {})",
+            power, best.fuel, best.position
+        );
+        return;
+    }
+
+    let linear = align(&crab_positions, &Cost::Linear).expect("Cannot align crabs");
+    println!("Part 1: {} (position {})", linear.fuel, linear.position);
+    let triangular = align(&crab_positions, &Cost::Triangular).expect("Cannot align crabs");
+    println!(
+        "Part 2: {} (position {})",
+        triangular.fuel, triangular.position
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::alignment::brute_force;
+    use super::*;
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = aoc::XorShift::new(0x9e3779b97f4a7c15);
+        let mut random = |bound: u64| rng.below(bound);
+        let costs = [
+            Cost::Linear,
+            Cost::Triangular,
+            Cost::Convex(Box::new(|d: i64| d.checked_mul(d))),
+            Cost::Convex(Box::new(|d| Some((d - 5).max(0) * 3))),
+        ];
+        for _ in 0..100 {
+            let n_crabs = 1 + random(40) as usize;
+            let crabs = (0..n_crabs)
+                .map(|_| random(200) as i64 - 100)
+                .collect::<Vec<_>>();
+            for cost in &costs {
+                assert_eq!(
+                    align(&crabs, cost).unwrap().fuel,
+                    brute_force(&crabs, cost).unwrap().fuel,
+                    "alignment differs from brute force for {:?}",
+                    crabs
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        assert!(align(&[], &Cost::Linear).is_err());
+        let ninth_power = Cost::Convex(Box::new(|d: i64| d.checked_pow(9)));
+        assert!(align(&[0, 2000, 4000], &ninth_power).is_err());
+        assert_eq!(
+            align(&[0, 10, 20], &ninth_power).unwrap().fuel,
+            2 * 10i64.pow(9)
+        );
+        assert!(align(&[0, 10_000_000_000], &Cost::Triangular).is_err());
+        let far = i64::MAX / 2;
+        assert!(align(&[-far, -far, 0, far, far], &Cost::Linear).is_err());
+    }
 }
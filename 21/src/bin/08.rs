@@ -1,24 +1,25 @@
 use anyhow::{bail, Context};
-use array_init::array_init;
 use std::str::FromStr;
 
-// TODO should be a bitset, but no bitset in std
+/// Lit segments, one bit per wire `a`, `b`, ...
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct Segments([bool; 7]);
+struct Segments(u32);
+
+const MAX_SEGMENTS: usize = 26;
 
 impl FromStr for Segments {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut result = [false; 7];
+        let mut result = 0;
         for c in s.chars() {
-            if c < 'a' || c > 'g' {
+            if !c.is_ascii_lowercase() {
                 bail!("Unknown character in segment");
             }
-            let index = c as usize - 'a' as usize;
-            if result[index] {
+            let bit = 1 << (c as usize - 'a' as usize);
+            if result & bit != 0 {
                 bail!("Duplicated segment");
             }
-            result[index] = true;
+            result |= bit;
         }
         Ok(Segments(result))
     }
@@ -26,31 +27,255 @@ impl FromStr for Segments {
 
 impl Segments {
     fn n_active(&self) -> usize {
-        self.0.iter().filter(|&&x| x).count()
+        self.0.count_ones() as usize
     }
+}
 
-    fn contains_all(&self, other: &Segments) -> bool {
-        self.0
-            .iter()
-            .zip(other.0.iter())
-            .map(|(&a, &b)| !b || b && a)
-            .all(|x| x)
+mod wiring {
+    use std::fmt;
+    use std::str::FromStr;
+
+    use anyhow::bail;
+
+    use super::{Segments, MAX_SEGMENTS};
+
+    /// The segments lit for each digit, indexed by the digit's value.
+    #[derive(Debug)]
+    pub struct GlyphTable {
+        n_segments: usize,
+        glyphs: Vec<Segments>,
+    }
+
+    impl FromStr for GlyphTable {
+        type Err = anyhow::Error;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let glyphs = s
+                .split_whitespace()
+                .map(Segments::from_str)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            if glyphs.is_empty() {
+                bail!("No glyphs supplied");
+            }
+            if glyphs
+                .iter()
+                .enumerate()
+                .any(|(i, g)| glyphs[..i].contains(g))
+            {
+                bail!("Glyphs are not distinct");
+            }
+            let n_segments = glyphs
+                .iter()
+                .map(|g| (u32::BITS - g.0.leading_zeros()) as usize)
+                .max()
+                .unwrap();
+            Ok(GlyphTable { n_segments, glyphs })
+        }
+    }
+
+    impl GlyphTable {
+        pub fn seven_segment() -> GlyphTable {
+            "abcefg cf acdeg acdfg bcdf abdfg abdefg acf abcdefg abcdfg"
+                .parse()
+                .unwrap()
+        }
+
+        pub fn len(&self) -> usize {
+            self.glyphs.len()
+        }
+
+        pub fn digit(&self, segments: Segments) -> Option<usize> {
+            self.glyphs.iter().position(|&g| g == segments)
+        }
+
+        /// Whether the digit is the only one lighting its number of segments.
+        pub fn has_unique_count(&self, digit: usize) -> bool {
+            let n = self.glyphs[digit].n_active();
+            self.glyphs.iter().filter(|g| g.n_active() == n).count() == 1
+        }
+
+        fn with_count(&self, n: usize) -> impl Iterator<Item = u32> + '_ {
+            self.glyphs
+                .iter()
+                .filter(move |g| g.n_active() == n)
+                .map(|g| g.0)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SolveError {
+        /// No wiring turns every observation into a glyph.
+        Inconsistent,
+        /// More than one wiring fits the observations.
+        Ambiguous,
+    }
+
+    impl fmt::Display for SolveError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                SolveError::Inconsistent => write!(f, "observations fit no wiring"),
+                SolveError::Ambiguous => write!(f, "observations fit more than one wiring"),
+            }
+        }
+    }
+
+    impl std::error::Error for SolveError {}
+
+    /// The segment each wire is connected to.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Wiring(Vec<usize>);
+
+    impl Wiring {
+        pub fn apply(&self, scrambled: Segments) -> Segments {
+            Segments(
+                self.0
+                    .iter()
+                    .enumerate()
+                    .filter(|(wire, _)| scrambled.0 >> wire & 1 == 1)
+                    .fold(0, |acc, (_, &segment)| acc | 1 << segment),
+            )
+        }
+    }
+
+    struct Solver<'a> {
+        table: &'a GlyphTable,
+        observed: &'a [Segments],
+        candidates: Vec<u32>,
+        order: Vec<usize>,
+        assigned: Vec<Option<usize>>,
+        solutions: Vec<Wiring>,
+    }
+
+    impl Solver<'_> {
+        // Every observation has to be lit by a glyph with as many segments, so its wires go to
+        // segments some such glyph lights, and the other wires avoid those all of them light.
+        fn restrict(&mut self) -> Result<(), SolveError> {
+            let full = (1 << self.table.n_segments) - 1;
+            for o in self.observed {
+                let (union, intersection) = self
+                    .table
+                    .with_count(o.n_active())
+                    .fold((0, full), |(u, i), g| (u | g, i & g));
+                if union == 0 {
+                    return Err(SolveError::Inconsistent);
+                }
+                for (wire, c) in self.candidates.iter_mut().enumerate() {
+                    *c &= if o.0 >> wire & 1 == 1 {
+                        union
+                    } else {
+                        !intersection
+                    };
+                }
+            }
+            self.propagate()
+        }
+
+        // Segments forced on one wire are unavailable to all others.
+        fn propagate(&mut self) -> Result<(), SolveError> {
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for wire in 0..self.candidates.len() {
+                    let c = self.candidates[wire];
+                    if c == 0 {
+                        return Err(SolveError::Inconsistent);
+                    }
+                    if c.count_ones() != 1 {
+                        continue;
+                    }
+                    for (other, o) in self.candidates.iter_mut().enumerate() {
+                        if other != wire && *o & c != 0 {
+                            *o &= !c;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        // A glyph with the right count must contain the segments of the assigned wires that are
+        // lit and none of those that are not.
+        fn is_feasible(&self) -> bool {
+            self.observed.iter().all(|o| {
+                let (mut on, mut off) = (0, 0);
+                for (wire, segment) in self.assigned.iter().enumerate() {
+                    if let Some(segment) = segment {
+                        if o.0 >> wire & 1 == 1 {
+                            on |= 1 << segment;
+                        } else {
+                            off |= 1 << segment;
+                        }
+                    }
+                }
+                self.table
+                    .with_count(o.n_active())
+                    .any(|g| g & on == on && g & off == 0)
+            })
+        }
+
+        fn search(&mut self, depth: usize, used: u32) {
+            if self.solutions.len() > 1 || !self.is_feasible() {
+                return;
+            }
+            let Some(&wire) = self.order.get(depth) else {
+                self.solutions
+                    .push(Wiring(self.assigned.iter().map(|s| s.unwrap()).collect()));
+                return;
+            };
+            let mut options = self.candidates[wire] & !used;
+            while options != 0 {
+                let segment = options.trailing_zeros() as usize;
+                options &= options - 1;
+                self.assigned[wire] = Some(segment);
+                self.search(depth + 1, used | 1 << segment);
+            }
+            self.assigned[wire] = None;
+        }
+    }
+
+    /// Finds the only wiring under which every observation shows a glyph of the table.
+    pub fn solve(table: &GlyphTable, observed: &[Segments]) -> Result<Wiring, SolveError> {
+        let n = table.n_segments;
+        if n > MAX_SEGMENTS || observed.iter().any(|o| o.0 >> n != 0) {
+            return Err(SolveError::Inconsistent);
+        }
+        let mut solver = Solver {
+            table,
+            observed,
+            candidates: vec![(1 << n) - 1; n],
+            order: (0..n).collect(),
+            assigned: vec![None; n],
+            solutions: vec![],
+        };
+        solver.restrict()?;
+        let candidates = &solver.candidates;
+        solver
+            .order
+            .sort_by_key(|&wire| candidates[wire].count_ones());
+        solver.search(0, 0);
+        match solver.solutions.len() {
+            0 => Err(SolveError::Inconsistent),
+            1 => Ok(solver.solutions.pop().unwrap()),
+            _ => Err(SolveError::Ambiguous),
+        }
     }
 }
 
+use wiring::{solve, GlyphTable};
+
 #[derive(Debug)]
-struct Display([i8; 4]);
+struct Display(Vec<usize>);
 
 impl Display {
-    fn value(&self) -> i32 {
-        self.0.iter().fold(0, |acc, &v| acc * 10 + v as i32)
+    fn value(&self, radix: usize) -> usize {
+        self.0.iter().fold(0, |acc, &v| acc * radix + v)
     }
 }
 
 #[derive(Debug)]
 struct ScrambledDisplay {
-    private: [Segments; 10],
-    public: [Segments; 4],
+    private: Vec<Segments>,
+    public: Vec<Segments>,
 }
 
 impl FromStr for ScrambledDisplay {
@@ -58,76 +283,123 @@ impl FromStr for ScrambledDisplay {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         const SEPARATOR: &str = " | ";
         let separator_id = s.find(SEPARATOR).context("missing separator")?;
-        let privates = s[..separator_id]
+        let private = s[..separator_id]
             .split(' ')
             .map(Segments::from_str)
             .collect::<anyhow::Result<Vec<_>>>()?;
-        let publics = s[separator_id + SEPARATOR.len()..]
+        let public = s[separator_id + SEPARATOR.len()..]
             .split(' ')
             .map(Segments::from_str)
             .collect::<anyhow::Result<Vec<_>>>()?;
-        if privates.len() != 10 || publics.len() != 4 {
-            bail!("Wrong number of digits");
-        }
-        Ok(ScrambledDisplay {
-            private: array_init(|i| privates[i]),
-            public: array_init(|i| publics[i]),
-        })
+        Ok(ScrambledDisplay { private, public })
     }
 }
 
 impl ScrambledDisplay {
-    fn find_digit<'a>(&'a self, f: impl Fn(&&Segments) -> bool) -> anyhow::Result<&'a Segments> {
-        self.private.iter().find(f).context("missing digit")
-    }
-
-    fn resolve(&self) -> anyhow::Result<[&Segments; 10]> {
-        let one = self.find_digit(|d| d.n_active() == 2)?;
-        let four = self.find_digit(|d| d.n_active() == 4)?;
-        let seven = self.find_digit(|d| d.n_active() == 3)?;
-        let eight = self.find_digit(|d| d.n_active() == 7)?;
-        let six = self.find_digit(|d| d.n_active() == 6 && !d.contains_all(seven))?;
-        let nine = self.find_digit(|d| d.n_active() == 6 && d.contains_all(four))?;
-        let zero = self.find_digit(|d| d.n_active() == 6 && **d != *nine && **d != *six)?;
-        let three = self.find_digit(|d| d.n_active() == 5 && d.contains_all(one))?;
-        let five = self.find_digit(|d| d.n_active() == 5 && six.contains_all(d))?;
-        let two = self.find_digit(|d| d.n_active() == 5 && **d != *three && **d != *five)?;
-        Ok([zero, one, two, three, four, five, six, seven, eight, nine])
-    }
-
-    fn read(&self) -> anyhow::Result<Display> {
-        let resolved = self.resolve()?;
-        let mut result = [0; 4];
-        for (index, segments) in self.public.iter().enumerate() {
-            result[index] = resolved
-                .iter()
-                .position(|s| **s == *segments)
-                .context("unknown display")? as i8;
-        }
-        Ok(Display(result))
+    fn read(&self, table: &GlyphTable) -> anyhow::Result<Display> {
+        let wiring = solve(table, &self.private)?;
+        self.public
+            .iter()
+            .map(|&s| table.digit(wiring.apply(s)).context("unknown display"))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(Display)
     }
 }
 
-fn is_simple(n: i8) -> bool {
-    n == 1 || n == 4 || n == 7 || n == 8
-}
-
 fn main() {
+    let table = match std::env::args().nth(1) {
+        Some(path) => std::fs::read_to_string(path)
+            .expect("Cannot read glyph table")
+            .parse()
+            .expect("Invalid glyph table"),
+        None => GlyphTable::seven_segment(),
+    };
+
     let displays = aoc::get_input(21, 8)
         .split('\n')
         .filter_map(|s| ScrambledDisplay::from_str(s).ok())
-        .map(|d| d.read())
+        .map(|d| d.read(&table))
         .collect::<anyhow::Result<Vec<_>>>()
         .expect("not able to read all displays");
     println!(
         "Part 1: {}",
         displays
             .iter()
-            .map(|d| d.0.iter().filter(|&&i| is_simple(i)).count())
+            .map(|d| d.0.iter().filter(|&&i| table.has_unique_count(i)).count())
             .sum::<usize>()
     );
     println!(
         "Part 2: {}",
-        displays.iter().map(Display::value).sum::<i32>()
+        displays.iter().map(|d| d.value(table.len())).sum::<usize>()
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiring::SolveError;
+
+    fn segments(patterns: &str) -> Vec<Segments> {
+        patterns.split(' ').map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn reads_example_line() {
+        let display =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"
+                .parse::<ScrambledDisplay>()
+                .unwrap();
+        let table = GlyphTable::seven_segment();
+        assert_eq!(display.read(&table).unwrap().value(table.len()), 5353);
+    }
+
+    #[test]
+    fn too_few_patterns_are_ambiguous() {
+        let table = GlyphTable::seven_segment();
+        assert_eq!(
+            solve(&table, &segments("ab dab")),
+            Err(SolveError::Ambiguous)
+        );
+    }
+
+    #[test]
+    fn unknown_patterns_are_inconsistent() {
+        let table = GlyphTable::seven_segment();
+        // No digit lights a single segment, nor more than seven
+        assert_eq!(solve(&table, &segments("a")), Err(SolveError::Inconsistent));
+        assert_eq!(
+            solve(&table, &segments("abcdefgh")),
+            Err(SolveError::Inconsistent)
+        );
+        // Two patterns of two segments but only the 1 lights two
+        assert_eq!(
+            solve(&table, &segments("ab cd")),
+            Err(SolveError::Inconsistent)
+        );
+    }
+
+    #[test]
+    fn solves_other_tables() {
+        // Six segments around a hexagon, several glyphs share a count
+        const GLYPHS: &str = "ab bc cd de ef fa abd bce cdf abcdef ace bdf";
+        let table = GLYPHS.parse::<GlyphTable>().unwrap();
+        let scramble = |glyph: &str| {
+            glyph
+                .chars()
+                .map(|c| "dfbace".as_bytes()[c as usize - 'a' as usize] as char)
+                .collect::<String>()
+        };
+        let glyphs = GLYPHS.split(' ');
+        let private = glyphs.clone().map(scramble).collect::<Vec<_>>();
+        let public = ["bdf", "ab", "ace", "fa"].map(scramble);
+        let display = format!("{} | {}", private.join(" "), public.join(" "))
+            .parse::<ScrambledDisplay>()
+            .unwrap();
+        assert_eq!(display.read(&table).unwrap().0, [11, 0, 10, 5]);
+
+        let wiring = solve(&table, &display.private).unwrap();
+        for (glyph, observed) in glyphs.zip(&display.private) {
+            assert_eq!(wiring.apply(*observed), glyph.parse().unwrap());
+        }
+    }
+}
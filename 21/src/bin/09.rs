@@ -55,42 +55,145 @@ impl FromStr for Grid {
     }
 }
 
-fn fill_basin(grid: &Grid, origin: usize, visited: &mut Vec<bool>) -> usize {
-    let mut count = 0;
-    let mut to_visit = vec![origin];
-    while !to_visit.is_empty() {
-        let next = to_visit.pop().unwrap();
-        if visited[next] {
-            continue;
+mod basins {
+    use super::Grid;
+
+    const WALL: i8 = 9;
+
+    struct UnionFind {
+        parents: Vec<usize>,
+    }
+
+    impl UnionFind {
+        fn new(n: usize) -> UnionFind {
+            UnionFind {
+                parents: (0..n).collect(),
+            }
         }
-        visited[next] = true;
-        if grid.data[next] != 9 {
-            count += 1;
-            for n in grid.neighbors(next) {
-                if !visited[n] && !to_visit.contains(&n) {
-                    to_visit.push(n);
-                }
+
+        fn find(&mut self, mut i: usize) -> usize {
+            while self.parents[i] != i {
+                self.parents[i] = self.parents[self.parents[i]];
+                i = self.parents[i];
             }
+            i
+        }
+
+        fn union(&mut self, a: usize, b: usize) {
+            let (a, b) = (self.find(a), self.find(b));
+            self.parents[a.max(b)] = a.min(b);
         }
     }
-    count
-}
 
-fn basins(grid: &Grid) -> Vec<usize> {
-    let mut basins = vec![];
-    let mut visited = vec![false; grid.data.len()];
-    for i in 0..visited.len() {
-        if !visited[i] {
-            let basin = fill_basin(grid, i, &mut visited);
-            if basin > 0 {
-                basins.push(basin);
+    #[derive(Debug)]
+    pub struct Basin {
+        pub low_point: usize,
+        pub cells: Vec<usize>,
+    }
+
+    /// Basin of every cell, walls belong to none.
+    #[derive(Debug)]
+    pub struct Labelling {
+        pub labels: Vec<Option<usize>>,
+        pub basins: Vec<Basin>,
+    }
+
+    impl Labelling {
+        // Joining each cell with its right and lower neighbour connects every basin.
+        pub fn new(grid: &Grid) -> Labelling {
+            let mut components = UnionFind::new(grid.data.len());
+            for i in 0..grid.data.len() {
+                if grid.data[i] == WALL {
+                    continue;
+                }
+                for n in grid.neighbors(i) {
+                    if n > i && grid.data[n] != WALL {
+                        components.union(i, n);
+                    }
+                }
+            }
+
+            let mut labels = vec![None; grid.data.len()];
+            let mut roots = vec![None; grid.data.len()];
+            let mut basins: Vec<Basin> = vec![];
+            for (i, cell) in labels.iter_mut().enumerate() {
+                if grid.data[i] == WALL {
+                    continue;
+                }
+                let root = components.find(i);
+                let label = *roots[root].get_or_insert_with(|| {
+                    basins.push(Basin {
+                        low_point: i,
+                        cells: vec![],
+                    });
+                    basins.len() - 1
+                });
+                let basin = &mut basins[label];
+                if grid.data[i] < grid.data[basin.low_point] {
+                    basin.low_point = i;
+                }
+                basin.cells.push(i);
+                *cell = Some(label);
+            }
+            Labelling { labels, basins }
+        }
+
+        /// Plain PPM with a colour per basin, darker where the floor is higher.
+        pub fn to_ppm(&self, grid: &Grid) -> String {
+            let n_rows = grid.data.len() / grid.n_columns;
+            let mut result = format!("P3\n{} {}\n255\n", grid.n_columns, n_rows);
+            for row in self
+                .labels
+                .chunks(grid.n_columns)
+                .zip(grid.data.chunks(grid.n_columns))
+            {
+                let pixels = row
+                    .0
+                    .iter()
+                    .zip(row.1.iter())
+                    .map(|(label, &height)| {
+                        let (r, g, b) = match label {
+                            Some(label) => colour(*label, height),
+                            None => (0, 0, 0),
+                        };
+                        format!("{} {} {}", r, g, b)
+                    })
+                    .collect::<Vec<_>>();
+                result.push_str(&pixels.join(" "));
+                result.push('\n');
             }
+            result
         }
     }
-    basins
+
+    // Hues spread by the golden angle so neighbouring labels look different.
+    fn colour(label: usize, height: i8) -> (u8, u8, u8) {
+        let hue = (label as f64 * 137.508) % 360.0;
+        let value = 1.0 - height as f64 / (2.0 * WALL as f64);
+        let x = 1.0 - ((hue / 60.0) % 2.0 - 1.0).abs();
+        let (r, g, b) = match (hue / 60.0) as u32 {
+            0 => (1.0, x, 0.0),
+            1 => (x, 1.0, 0.0),
+            2 => (0.0, 1.0, x),
+            3 => (0.0, x, 1.0),
+            4 => (x, 0.0, 1.0),
+            _ => (1.0, 0.0, x),
+        };
+        let scale = |c: f64| (c * value * 255.0).round() as u8;
+        (scale(r), scale(g), scale(b))
+    }
 }
 
+use basins::Labelling;
+
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let ppm_path = match args.as_slice() {
+        [] => None,
+        [flag, path] if flag == "--ppm" => Some(path),
+        _ => panic!("Usage: 09 [--ppm PATH]"),
+    };
+
     let caves = Grid::from_str(aoc::get_input(21, 9).trim()).expect("Invalid grid provided");
     let local_minimums = (0..caves.data.len())
         .map(|i| (i, caves.data[i]))
@@ -105,10 +208,47 @@ fn main() {
             .sum::<i32>()
     );
 
-    let mut basins = basins(&caves);
-    basins.sort();
-    println!(
-        "Part 2: {}",
-        basins.iter().rev().take(3).fold(1, |acc, v| acc * v)
-    );
+    let labelling = Labelling::new(&caves);
+    let mut sizes = labelling
+        .basins
+        .iter()
+        .map(|b| b.cells.len())
+        .collect::<Vec<_>>();
+    sizes.sort();
+    println!("Part 2: {}", sizes.iter().rev().take(3).product::<usize>());
+
+    if let Some(path) = ppm_path {
+        std::fs::write(path, labelling.to_ppm(&caves)).expect("Cannot write image");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2199943210\n3987894921\n9856789892\n8767896789\n9899965678";
+
+    #[test]
+    fn labels_example_basins() {
+        let caves = Grid::from_str(EXAMPLE).unwrap();
+        let labelling = Labelling::new(&caves);
+        let mut sizes = labelling
+            .basins
+            .iter()
+            .map(|b| b.cells.len())
+            .collect::<Vec<_>>();
+        sizes.sort();
+        assert_eq!(sizes, [3, 9, 9, 14]);
+
+        for (i, basin) in labelling.basins.iter().enumerate() {
+            assert!(basin.cells.iter().all(|&c| labelling.labels[c] == Some(i)));
+            assert!(caves
+                .neighbors(basin.low_point)
+                .into_iter()
+                .all(|n| caves.data[n] > caves.data[basin.low_point]));
+        }
+        for (cell, label) in labelling.labels.iter().enumerate() {
+            assert_eq!(label.is_none(), caves.data[cell] == 9);
+        }
+    }
 }
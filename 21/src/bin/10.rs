@@ -1,91 +1,201 @@
-use anyhow::Context;
+use std::{fs, str::FromStr};
 
-const ILLEGAL_CHAR_SCORE: [(char, i32); 4] = [(')', 3), (']', 57), ('}', 1197), ('>', 25137)];
-fn corrupted_score(c: char) -> anyhow::Result<i32> {
-    ILLEGAL_CHAR_SCORE
-        .iter()
-        .find(|(ch, _)| *ch == c)
-        .map(|(_, score)| *score)
-        .context("Unknown character to score")
-}
+const DEFAULT_RULES: &str = include_str!("../../rules/10.txt");
 
-const MISSING_CHAR_SCORE: [(char, i64); 4] = [(')', 1), (']', 2), ('}', 3), ('>', 4)];
-fn incomplete_score(v: &[char]) -> anyhow::Result<i64> {
-    let values = v
-        .iter()
-        .map(|c| {
-            MISSING_CHAR_SCORE
-                .iter()
-                .find(|(ch, _)| ch == c)
-                .map(|(_, v)| *v)
-        })
-        .collect::<Option<Vec<_>>>()
-        .context("Unknown character")?;
-    Ok(values.into_iter().fold(0, |acc, v| acc * 5 + v))
-}
+mod syntax {
+    use std::str::FromStr;
 
-#[derive(Debug)]
-enum LineType {
-    Corrupted(char),
-    Incomplete(Vec<char>),
-}
+    use anyhow::{bail, Context};
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Bracket {
+        pub open: char,
+        pub close: char,
+        pub illegal_score: i64,
+        pub completion_score: i64,
+    }
+
+    /// Bracket pairs with their scores, one pair per line and a `base` line.
+    #[derive(Debug)]
+    pub struct Rules {
+        brackets: Vec<Bracket>,
+        completion_base: i64,
+    }
+
+    fn single_char(s: &str) -> anyhow::Result<char> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => bail!("'{}' is not a single character", s),
+        }
+    }
 
-fn classify_line(s: &str) -> LineType {
-    let mut expected = vec![];
-    for c in s.chars() {
-        match c {
-            '(' => expected.push(')'),
-            '[' => expected.push(']'),
-            '{' => expected.push('}'),
-            '<' => expected.push('>'),
-            _ => {
-                if let Some(v) = expected.pop() {
-                    if v != c {
-                        return LineType::Corrupted(c);
+    impl FromStr for Rules {
+        type Err = anyhow::Error;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut brackets: Vec<Bracket> = vec![];
+            let mut completion_base = None;
+            for line in s.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let parts = line.split_whitespace().collect::<Vec<_>>();
+                match parts.as_slice() {
+                    ["base", base] => {
+                        completion_base = Some(base.parse().context("Invalid base")?);
                     }
+                    [open, close, illegal, completion] => {
+                        let bracket = Bracket {
+                            open: single_char(open)?,
+                            close: single_char(close)?,
+                            illegal_score: illegal.parse().context("Invalid score")?,
+                            completion_score: completion.parse().context("Invalid score")?,
+                        };
+                        let used = |c| brackets.iter().any(|b| b.open == c || b.close == c);
+                        if bracket.open == bracket.close
+                            || used(bracket.open)
+                            || used(bracket.close)
+                        {
+                            bail!("Bracket '{}' reuses a character", line);
+                        }
+                        brackets.push(bracket);
+                    }
+                    _ => bail!("Unknown rule '{}'", line),
                 }
             }
+            Ok(Rules {
+                brackets,
+                completion_base: completion_base.context("Missing completion base")?,
+            })
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Status {
+        /// `found` does not close the innermost open bracket, `expected` is `None` when no
+        /// bracket is open.
+        Corrupted {
+            column: usize,
+            found: char,
+            expected: Option<char>,
+        },
+        /// Closers that would complete the line, empty when it is already complete.
+        Incomplete(String),
+    }
+
+    impl Rules {
+        fn by_open(&self, c: char) -> Option<&Bracket> {
+            self.brackets.iter().find(|b| b.open == c)
+        }
+
+        fn by_close(&self, c: char) -> Option<&Bracket> {
+            self.brackets.iter().find(|b| b.close == c)
+        }
+
+        /// Columns are counted from 1.
+        pub fn check(&self, line: &str) -> anyhow::Result<Status> {
+            let mut expected = vec![];
+            for (i, c) in line.chars().enumerate() {
+                if let Some(bracket) = self.by_open(c) {
+                    expected.push(bracket.close);
+                } else if self.by_close(c).is_some() {
+                    match expected.pop() {
+                        Some(e) if e == c => {}
+                        e => {
+                            return Ok(Status::Corrupted {
+                                column: i + 1,
+                                found: c,
+                                expected: e,
+                            })
+                        }
+                    }
+                } else {
+                    bail!("Unknown character '{}' in column {}", c, i + 1);
+                }
+            }
+            Ok(Status::Incomplete(expected.into_iter().rev().collect()))
+        }
+
+        pub fn illegal_score(&self, c: char) -> Option<i64> {
+            self.by_close(c).map(|b| b.illegal_score)
+        }
+
+        pub fn completion_score(&self, completion: &str) -> Option<i64> {
+            completion.chars().try_fold(0i64, |acc, c| {
+                acc.checked_mul(self.completion_base)?
+                    .checked_add(self.by_close(c)?.completion_score)
+            })
         }
     }
-    expected.reverse();
-    LineType::Incomplete(expected)
 }
 
+use syntax::{Rules, Status};
+
 fn main() {
+    let mut report = false;
+    let mut rules_file = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--report" => report = true,
+            _ if rules_file.is_none() => rules_file = Some(arg),
+            _ => panic!("Usage: 10 [--report] [RULES_FILE]"),
+        }
+    }
+    let rules = match rules_file {
+        Some(path) => fs::read_to_string(path).expect("Cannot read rules file"),
+        None => DEFAULT_RULES.to_owned(),
+    };
+    let rules = Rules::from_str(&rules).expect("Invalid rules");
+
     let lines = aoc::get_input(21, 10)
         .trim()
         .split('\n')
-        .map(str::to_owned)
-        .map(|s| classify_line(&s))
-        .collect::<Vec<_>>();
+        .map(|s| rules.check(s))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("Illegal character in input");
 
-    let corrupted_scores = lines
-        .iter()
-        .filter_map(|s| {
-            if let LineType::Corrupted(c) = s {
-                Some(corrupted_score(*c))
-            } else {
-                None
+    if report {
+        for (i, status) in lines.iter().enumerate() {
+            match status {
+                Status::Corrupted {
+                    column,
+                    found,
+                    expected: Some(e),
+                } => println!("{}:{}: expected '{}', found '{}'", i + 1, column, e, found),
+                Status::Corrupted {
+                    column,
+                    found,
+                    expected: None,
+                } => println!("{}:{}: unexpected '{}'", i + 1, column, found),
+                Status::Incomplete(completion) if completion.is_empty() => {
+                    println!("{}: complete", i + 1)
+                }
+                Status::Incomplete(completion) => {
+                    println!("{}: complete with {}", i + 1, completion)
+                }
             }
+        }
+    }
+
+    let corrupted_score = lines
+        .iter()
+        .filter_map(|s| match s {
+            Status::Corrupted { found, .. } => rules.illegal_score(*found),
+            Status::Incomplete(_) => None,
         })
-        .collect::<anyhow::Result<Vec<_>>>()
-        .expect("Illegal character in input");
-    println!("Part 1: {}", corrupted_scores.into_iter().sum::<i32>());
+        .sum::<i64>();
+    println!("Part 1: {}", corrupted_score);
 
     let mut incomplete_scores = lines
-        .into_iter()
-        .filter_map(|s| {
-            if let LineType::Incomplete(inc) = s {
-                Some(incomplete_score(&inc))
-            } else {
-                None
+        .iter()
+        .filter_map(|s| match s {
+            Status::Incomplete(completion) if !completion.is_empty() => {
+                Some(rules.completion_score(completion))
             }
+            _ => None,
         })
-        .collect::<anyhow::Result<Vec<_>>>()
-        .expect("Illegal character in input");
+        .collect::<Option<Vec<_>>>()
+        .expect("Completion score overflows");
     incomplete_scores.sort();
-    println!(
-        "Part 2: {}",
-        incomplete_scores[(incomplete_scores.len() / 2)]
-    );
+    println!("Part 2: {}", incomplete_scores[incomplete_scores.len() / 2]);
 }
@@ -57,6 +57,9 @@ impl FromStr for Grid {
             bail!("No rows supplied");
         }
         let n_columns = rows[0].len();
+        if n_columns == 0 {
+            bail!("Empty rows supplied");
+        }
         if rows.iter().skip(1).any(|v| v.len() != n_columns) {
             bail!("Not all rows of the same size");
         }
@@ -67,47 +70,173 @@ impl FromStr for Grid {
     }
 }
 
-fn simulate_step(grid: &mut Grid) -> usize {
-    let mut to_trigger = (0..grid.data.len()).collect::<Vec<_>>();
-    let mut triggered = vec![false; grid.data.len()];
-    while !to_trigger.is_empty() {
-        let i = to_trigger.pop().unwrap();
-        grid.data[i] += 1;
-        if grid.data[i] > 9 && !triggered[i] {
-            triggered[i] = true;
-            to_trigger.append(&mut grid.neighbors(i));
+mod octopus {
+    use std::collections::HashMap;
+
+    use super::Grid;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Cycle {
+        pub start: usize,
+        pub period: usize,
+    }
+
+    /// Every octopus that flashed in each step, and the step at which each energy state was first
+    /// reached.
+    pub struct Simulation {
+        grid: Grid,
+        flashes: Vec<Vec<usize>>,
+        seen: HashMap<Vec<i8>, usize>,
+        cycle: Option<Cycle>,
+    }
+
+    impl Simulation {
+        pub fn new(grid: Grid) -> Simulation {
+            let seen = HashMap::from([(grid.data.clone(), 0)]);
+            Simulation {
+                grid,
+                flashes: vec![],
+                seen,
+                cycle: None,
+            }
+        }
+
+        pub fn grid(&self) -> &Grid {
+            &self.grid
+        }
+
+        pub fn n_steps(&self) -> usize {
+            self.flashes.len()
+        }
+
+        /// Octopuses that flashed during the given step, counted from 1.
+        pub fn flashed(&self, step: usize) -> &[usize] {
+            &self.flashes[step - 1]
+        }
+
+        pub fn cycle(&self) -> Option<Cycle> {
+            self.cycle
+        }
+
+        pub fn step(&mut self) -> &[usize] {
+            let grid = &mut self.grid;
+            let mut to_trigger = (0..grid.data.len()).collect::<Vec<_>>();
+            let mut triggered = vec![false; grid.data.len()];
+            let mut flashed = vec![];
+            while let Some(i) = to_trigger.pop() {
+                grid.data[i] += 1;
+                if grid.data[i] > 9 && !triggered[i] {
+                    triggered[i] = true;
+                    flashed.push(i);
+                    to_trigger.append(&mut grid.neighbors(i));
+                }
+            }
+            for &i in &flashed {
+                grid.data[i] = 0;
+            }
+            flashed.sort_unstable();
+            self.flashes.push(flashed);
+
+            let step = self.flashes.len();
+            if self.cycle.is_none() {
+                if let Some(&start) = self.seen.get(&self.grid.data) {
+                    self.cycle = Some(Cycle {
+                        start,
+                        period: step - start,
+                    });
+                } else {
+                    self.seen.insert(self.grid.data.clone(), step);
+                }
+            }
+            self.flashes.last().unwrap()
+        }
+
+        pub fn is_synchronized(&self, step: usize) -> bool {
+            self.flashed(step).len() == self.grid.data.len()
         }
     }
-    for i in 0..grid.data.len() {
-        if grid.data[i] > 9 {
-            grid.data[i] = 0;
+}
+
+use octopus::Simulation;
+
+const MAX_STEPS: usize = 1_000_000;
+
+// Flashing octopuses are bold white, the others get brighter as their energy rises.
+fn render(grid: &Grid, step: usize) -> String {
+    let mut result = format!("\x1b[2J\x1b[HStep {}\n", step);
+    for row in grid.data.chunks(grid.n_columns) {
+        for &energy in row {
+            if energy == 0 {
+                result.push_str("\x1b[1;97m0");
+            } else {
+                result.push_str(&format!("\x1b[38;5;{}m{}", 234 + 2 * energy as i32, energy));
+            }
         }
+        result.push_str("\x1b[0m\n");
     }
-    triggered.into_iter().filter(|&x| x).count()
+    result
 }
 
 fn main() {
-    let mut octopuses = Grid::from_str(aoc::get_input(21, 11).trim()).expect("Invalid input");
+    let mut show_cycle = false;
+    let mut animate = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cycle" => show_cycle = true,
+            "--animate" => {
+                let steps = args
+                    .next()
+                    .map(|s| s.parse().expect("Invalid number of steps"));
+                animate = Some(steps.unwrap_or(100));
+            }
+            _ => panic!("Usage: 11 [--cycle] [--animate [STEPS]]"),
+        }
+    }
 
-    let mut synced = None;
-    let mut flashes = 0;
-    for i in 1..=100 {
-        let result = simulate_step(&mut octopuses);
-        flashes += result;
-        if result == octopuses.data.len() {
-            synced = Some(i);
+    let octopuses = Grid::from_str(aoc::get_input(21, 11).trim()).expect("Invalid input");
+    let mut simulation = Simulation::new(octopuses);
+
+    if let Some(steps) = animate {
+        print!("{}", render(simulation.grid(), 0));
+        for step in 1..=steps {
+            simulation.step();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            print!("{}", render(simulation.grid(), step));
         }
+        return;
     }
-    println!("Part 1: {}", flashes);
 
-    for i in 101.. {
-        if synced.is_some() {
+    let mut synced = None;
+    while simulation.n_steps() < 100
+        || synced.is_none()
+        || (show_cycle && simulation.cycle().is_none())
+    {
+        if simulation.n_steps() == MAX_STEPS {
             break;
         }
-        let result = simulate_step(&mut octopuses);
-        if result == octopuses.data.len() {
-            synced = Some(i);
+        simulation.step();
+        if synced.is_none() && simulation.is_synchronized(simulation.n_steps()) {
+            synced = Some(simulation.n_steps());
+        }
+    }
+    println!(
+        "Part 1: {}",
+        (1..=100)
+            .map(|step| simulation.flashed(step).len())
+            .sum::<usize>()
+    );
+    match synced {
+        Some(step) => println!("Part 2: {}", step),
+        None => println!("Part 2: not synchronized within {} steps", MAX_STEPS),
+    }
+    if show_cycle {
+        match simulation.cycle() {
+            Some(cycle) => println!(
+                "Cycle after step {} with period {}",
+                cycle.start, cycle.period
+            ),
+            None => println!("No cycle within {} steps", MAX_STEPS),
         }
     }
-    println!("Part 2: {}", synced.unwrap());
 }
@@ -1,55 +1,211 @@
-use std::collections::{HashMap, HashSet};
-
 use anyhow::bail;
+use aoc::{EdgeKind, Graph, Storage};
+
+mod caves {
+    use std::collections::HashMap;
+
+    use anyhow::{bail, Context};
+    use aoc::Graph;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct State {
+        cave: usize,
+        /// Small caves entered at least once, by their bit.
+        visited: u64,
+        /// Small caves entered twice.
+        revisited: u64,
+        remaining: usize,
+    }
+
+    /// The cave system with small caves numbered for bitmasks.
+    pub struct Caves<'a> {
+        graph: &'a Graph,
+        small_bits: Vec<Option<u32>>,
+        start: usize,
+        end: usize,
+    }
+
+    impl<'a> Caves<'a> {
+        pub fn new(graph: &'a Graph) -> anyhow::Result<Caves<'a>> {
+            let start = graph.index("start").context("No start cave")?;
+            let end = graph.index("end").context("No end cave")?;
+            let mut n_small = 0;
+            let small_bits = (0..graph.len())
+                .map(|i| {
+                    if graph.name(i).chars().any(char::is_uppercase) {
+                        None
+                    } else {
+                        n_small += 1;
+                        Some(n_small - 1)
+                    }
+                })
+                .collect::<Vec<_>>();
+            if n_small > u64::BITS {
+                bail!("Too many small caves");
+            }
+            let is_big = |i: usize| small_bits[i].is_none();
+            if (0..graph.len()).any(|i| is_big(i) && graph.neighbors(i).any(|(n, _)| is_big(n))) {
+                bail!("Connected big caves allow endless paths");
+            }
+            Ok(Caves {
+                graph,
+                small_bits,
+                start,
+                end,
+            })
+        }
+
+        /// Paths from start to end where up to `revisits` small caves other than the start can be
+        /// entered twice.
+        pub fn count_paths(&self, revisits: usize) -> u64 {
+            self.count_from(self.initial(revisits), &mut HashMap::new())
+        }
+
+        /// The same paths as `count_paths`, lazily and in depth-first order.
+        pub fn paths(&self, revisits: usize) -> Paths<'_> {
+            Paths {
+                caves: self,
+                stack: vec![Frame {
+                    state: self.initial(revisits),
+                    next: 0,
+                }],
+            }
+        }
+
+        fn initial(&self, revisits: usize) -> State {
+            let outside = State {
+                cave: self.start,
+                visited: 0,
+                revisited: 0,
+                remaining: revisits,
+            };
+            self.enter(outside, self.start).unwrap()
+        }
 
-fn to_edge_map(edges: Vec<(String, String)>) -> HashMap<String, Vec<String>> {
-    let mut result = HashMap::new();
-    for e in edges {
-        if !result.contains_key(&e.0) {
-            result.insert(e.0.clone(), vec![]);
+        fn enter(&self, from: State, cave: usize) -> Option<State> {
+            let bit = match self.small_bits[cave] {
+                Some(bit) => 1 << bit,
+                None => return Some(State { cave, ..from }),
+            };
+            if from.visited & bit == 0 {
+                Some(State {
+                    cave,
+                    visited: from.visited | bit,
+                    ..from
+                })
+            } else if cave != self.start && from.revisited & bit == 0 && from.remaining > 0 {
+                Some(State {
+                    cave,
+                    revisited: from.revisited | bit,
+                    remaining: from.remaining - 1,
+                    ..from
+                })
+            } else {
+                None
+            }
         }
-        if !result.contains_key(&e.1) {
-            result.insert(e.1.clone(), vec![]);
+
+        fn count_from(&self, state: State, memo: &mut HashMap<State, u64>) -> u64 {
+            if state.cave == self.end {
+                return 1;
+            }
+            if let Some(&count) = memo.get(&state) {
+                return count;
+            }
+            let count = self
+                .graph
+                .neighbors(state.cave)
+                .filter_map(|(next, _)| self.enter(state, next))
+                .map(|next| self.count_from(next, memo))
+                .sum();
+            memo.insert(state, count);
+            count
         }
-        result
-            .get_mut(&e.0)
-            .iter_mut()
-            .for_each(|v| v.push(e.1.clone()));
-        result
-            .get_mut(&e.1)
-            .iter_mut()
-            .for_each(|v| v.push(e.0.clone()));
     }
-    result
+    struct Frame {
+        state: State,
+        /// Index of the next neighbour to try.
+        next: usize,
+    }
+
+    pub struct Paths<'a> {
+        caves: &'a Caves<'a>,
+        stack: Vec<Frame>,
+    }
+
+    impl<'a> Iterator for Paths<'a> {
+        type Item = Vec<&'a str>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let caves = self.caves;
+            loop {
+                let frame = self.stack.last_mut()?;
+                let Some((cave, _)) = caves.graph.neighbors(frame.state.cave).nth(frame.next)
+                else {
+                    self.stack.pop();
+                    continue;
+                };
+                frame.next += 1;
+                let Some(state) = caves.enter(frame.state, cave) else {
+                    continue;
+                };
+                if cave == caves.end {
+                    let mut path = self
+                        .stack
+                        .iter()
+                        .map(|f| caves.graph.name(f.state.cave))
+                        .collect::<Vec<_>>();
+                    path.push(caves.graph.name(cave));
+                    return Some(path);
+                }
+                self.stack.push(Frame { state, next: 0 });
+            }
+        }
+    }
+}
+
+use caves::Caves;
+
+#[derive(Debug, Default)]
+struct Filter {
+    length: Option<usize>,
+    through: Vec<String>,
+}
+
+impl Filter {
+    fn accepts(&self, path: &[&str]) -> bool {
+        self.length.is_none_or(|l| path.len() == l)
+            && self.through.iter().all(|c| path.contains(&c.as_str()))
+    }
 }
 
-fn count_paths<'a>(
-    graph: &'a HashMap<String, Vec<String>>,
-    current: &'a str,
-    visited: &mut HashSet<&'a str>,
-    revisited: bool,
-) -> u64 {
-    if current == "end" {
-        return 1;
-    }
-
-    let mut result = 0;
-    if !current.chars().any(char::is_uppercase) {
-        visited.insert(current);
-    }
-    for e in graph.get(current).unwrap() {
-        let did_visit = visited.contains(&e as &str);
-        if !did_visit {
-            result += count_paths(graph, e, &mut visited.clone(), revisited);
-        } else if e != "start" && !revisited {
-            result += count_paths(graph, e, &mut visited.clone(), true);
+fn json_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
         }
     }
+    result.push('"');
     result
 }
 
-fn main() {
-    let edges = aoc::get_input(21, 12)
+fn to_json(paths: &[Vec<&str>]) -> String {
+    let rows = paths
+        .iter()
+        .map(|p| {
+            let names = p.iter().map(|c| json_string(c)).collect::<Vec<_>>();
+            format!("  [{}]", names.join(", "))
+        })
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+fn parse_graph(s: &str) -> anyhow::Result<Graph> {
+    let edges = s
         .trim()
         .split('\n')
         .map(|s| {
@@ -57,19 +213,189 @@ fn main() {
             if parts.len() != 2 {
                 bail!("Wrong number of parts")
             } else {
-                Ok((parts[0].to_owned(), parts[1].to_owned()))
+                Ok((parts[0], parts[1], ()))
             }
         })
-        .collect::<anyhow::Result<Vec<_>>>()
-        .expect("Wrong input");
-    let graph = to_edge_map(edges);
-
-    println!(
-        "Part 1: {}",
-        count_paths(&graph, "start", &mut HashSet::new(), true)
-    );
-    println!(
-        "Part 2: {}",
-        count_paths(&graph, "start", &mut HashSet::new(), false)
-    );
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Graph::from_edges(
+        EdgeKind::Undirected,
+        Storage::Sparse,
+        edges,
+    ))
+}
+
+fn main() {
+    let graph = parse_graph(&aoc::get_input(21, 12)).expect("Wrong input");
+    if std::env::args().any(|a| a == "--dot") {
+        print!("{}", graph.to_dot(|_| None));
+        return;
+    }
+    let caves = Caves::new(&graph).expect("Invalid cave system");
+
+    const USAGE: &str =
+        "Usage: 12 [--dot | --revisits K] [--list [--json] [--length N] [--through CAVE]...]";
+    let mut revisits = None;
+    let mut list = false;
+    let mut json = false;
+    let mut filter = Filter::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--revisits" => {
+                revisits = Some(
+                    args.next()
+                        .and_then(|k| k.parse().ok())
+                        .expect("Invalid number of revisits"),
+                )
+            }
+            "--list" => list = true,
+            "--json" => json = true,
+            "--length" => {
+                filter.length = Some(
+                    args.next()
+                        .and_then(|l| l.parse().ok())
+                        .expect("Invalid path length"),
+                )
+            }
+            "--through" => filter.through.push(args.next().expect(USAGE)),
+            _ => panic!("{}", USAGE),
+        }
+    }
+
+    if list {
+        let paths = caves
+            .paths(revisits.unwrap_or(0))
+            .filter(|p| filter.accepts(p))
+            .collect::<Vec<_>>();
+        if json {
+            print!("{}", to_json(&paths));
+        } else {
+            for path in &paths {
+                println!("{}", path.join(","));
+            }
+        }
+        return;
+    }
+    if let Some(revisits) = revisits {
+        println!(
+            "Paths with up to {} revisits: {}",
+            revisits,
+            caves.count_paths(revisits)
+        );
+        return;
+    }
+
+    println!("Part 1: {}", caves.count_paths(0));
+    println!("Part 2: {}", caves.count_paths(1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: [(&str, u64, u64); 3] = [
+        ("start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end", 10, 36),
+        (
+            "dc-end\nHN-start\nstart-kj\ndc-start\ndc-HN\nLN-dc\nHN-end\nkj-sa\nkj-HN\nkj-dc",
+            19,
+            103,
+        ),
+        (
+            "fs-end\nhe-DX\nfs-he\nstart-DX\npj-DX\nend-zg\nzg-sl\nzg-pj\npj-he\nRW-he\nfs-DX\n\
+             pj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW",
+            226,
+            3509,
+        ),
+    ];
+
+    #[test]
+    fn counts_example_paths() {
+        for (input, part_1, part_2) in EXAMPLES {
+            let graph = parse_graph(input).unwrap();
+            let caves = Caves::new(&graph).unwrap();
+            assert_eq!(caves.count_paths(0), part_1);
+            assert_eq!(caves.count_paths(1), part_2);
+            assert_eq!(caves.paths(0).count() as u64, part_1);
+            assert_eq!(caves.paths(1).count() as u64, part_2);
+        }
+    }
+
+    const FIRST_EXAMPLE_PATHS: [&str; 10] = [
+        "start,A,b,A,c,A,end",
+        "start,A,b,A,end",
+        "start,A,b,end",
+        "start,A,c,A,b,A,end",
+        "start,A,c,A,b,end",
+        "start,A,c,A,end",
+        "start,A,end",
+        "start,b,A,c,A,end",
+        "start,b,A,end",
+        "start,b,end",
+    ];
+
+    fn listed(caves: &Caves, filter: &Filter) -> Vec<String> {
+        let mut paths = caves
+            .paths(0)
+            .filter(|p| filter.accepts(p))
+            .map(|p| p.join(","))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn lists_example_paths() {
+        let graph = parse_graph(EXAMPLES[0].0).unwrap();
+        let caves = Caves::new(&graph).unwrap();
+        assert_eq!(listed(&caves, &Filter::default()), FIRST_EXAMPLE_PATHS);
+    }
+
+    #[test]
+    fn filters_example_paths() {
+        let graph = parse_graph(EXAMPLES[0].0).unwrap();
+        let caves = Caves::new(&graph).unwrap();
+        let by_length = Filter {
+            length: Some(4),
+            through: vec![],
+        };
+        assert_eq!(
+            listed(&caves, &by_length),
+            ["start,A,b,end", "start,b,A,end"]
+        );
+        let through_c = Filter {
+            length: None,
+            through: vec!["c".to_owned()],
+        };
+        assert_eq!(
+            listed(&caves, &through_c),
+            [
+                "start,A,b,A,c,A,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,b,A,c,A,end",
+            ]
+        );
+        let both = Filter {
+            length: Some(6),
+            through: vec!["b".to_owned(), "c".to_owned()],
+        };
+        assert_eq!(
+            listed(&caves, &both),
+            ["start,A,c,A,b,end", "start,b,A,c,A,end"]
+        );
+        let nowhere = Filter {
+            length: None,
+            through: vec!["d".to_owned()],
+        };
+        assert!(listed(&caves, &nowhere).is_empty());
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(
+            to_json(&[vec!["a\"b", "c\\d"]]),
+            "[\n  [\"a\\\"b\", \"c\\\\d\"]\n]\n"
+        );
+    }
 }
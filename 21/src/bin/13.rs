@@ -103,7 +103,7 @@ fn display(points: &[Point]) {
                 print!(" ");
             }
         }
-        println!("");
+        println!();
     }
 }
 
@@ -130,10 +130,15 @@ fn main() {
 
     let after_first_fold = apply(points, &folds[0]);
     println!("Part 1: {}", after_first_fold.len());
-    println!("Part 2:");
     let code = folds
         .iter()
         .skip(1)
         .fold(after_first_fold, |v, f| apply(&v, f));
-    display(&code);
+    match aoc::recognize(code.iter().map(|p| (p.x as i64, p.y as i64))) {
+        Ok(text) => println!("Part 2: {}", text),
+        Err(e) => {
+            println!("Part 2: {}", e);
+            display(&code);
+        }
+    }
 }
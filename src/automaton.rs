@@ -0,0 +1,369 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+
+/// Row and column of a cell.
+pub type Cell = (i64, i64);
+
+/// Outer-totalistic rule in B/S notation, e.g. `B3/S23`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_counts = |part: &str, prefix: char| {
+            let digits = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .with_context(|| format!("Expected '{}' in rule", prefix))?;
+            let mut result = [false; 9];
+            for c in digits.chars() {
+                match c.to_digit(10) {
+                    Some(d) if d < 9 => result[d as usize] = true,
+                    _ => bail!("Invalid neighbour count '{}'", c),
+                }
+            }
+            Ok(result)
+        };
+        let (birth, survival) = s.trim().split_once('/').context("Missing '/' in rule")?;
+        Ok(Rule {
+            birth: parse_counts(birth, 'B')?,
+            survival: parse_counts(survival, 'S')?,
+        })
+    }
+}
+
+impl Rule {
+    pub fn is_alive_next(&self, alive: bool, neighbours: usize) -> bool {
+        if alive {
+            self.survival[neighbours]
+        } else {
+            self.birth[neighbours]
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// Cells outside of the board are always dead.
+    Bounded,
+    /// Edges wrap around.
+    Toroidal,
+    /// The board grows as needed, its size only matters for display.
+    Unbounded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// First step of the repeating part.
+    pub start: usize,
+    /// Length of the cycle, 1 for a stable board.
+    pub period: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    rule: Rule,
+    boundary: Boundary,
+    n_rows: i64,
+    n_columns: i64,
+    pinned_on: HashSet<Cell>,
+    pinned_off: HashSet<Cell>,
+    alive: HashSet<Cell>,
+}
+
+impl Automaton {
+    /// B0 rules would bring the whole plane alive on an unbounded board, so they are rejected.
+    pub fn new(
+        rule: Rule,
+        boundary: Boundary,
+        n_rows: i64,
+        n_columns: i64,
+    ) -> anyhow::Result<Automaton> {
+        if rule.birth[0] && boundary == Boundary::Unbounded {
+            bail!("B0 rules need a bounded or toroidal board");
+        }
+        Ok(Automaton {
+            rule,
+            boundary,
+            n_rows,
+            n_columns,
+            pinned_on: HashSet::new(),
+            pinned_off: HashSet::new(),
+            alive: HashSet::new(),
+        })
+    }
+
+    /// Reads a board of `#` (alive) and `.` (dead) cells.
+    pub fn parse(s: &str, rule: Rule, boundary: Boundary) -> anyhow::Result<Automaton> {
+        let rows = s.lines().collect::<Vec<_>>();
+        if rows.is_empty() {
+            bail!("No rows supplied");
+        }
+        let n_columns = rows[0].chars().count();
+        if rows.iter().skip(1).any(|r| r.chars().count() != n_columns) {
+            bail!("Not all rows of the same size");
+        }
+        let mut result = Automaton::new(rule, boundary, rows.len() as i64, n_columns as i64)?;
+        for (r, row) in rows.iter().enumerate() {
+            for (c, ch) in row.chars().enumerate() {
+                match ch {
+                    '#' => result.set((r as i64, c as i64), true),
+                    '.' => {}
+                    _ => bail!("Invalid cell '{}'", ch),
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn n_rows(&self) -> i64 {
+        self.n_rows
+    }
+
+    pub fn n_columns(&self) -> i64 {
+        self.n_columns
+    }
+
+    /// Corners of the board, the natural candidates for pinning.
+    pub fn corners(&self) -> [Cell; 4] {
+        let (r, c) = (self.n_rows - 1, self.n_columns - 1);
+        [(0, 0), (0, c), (r, 0), (r, c)]
+    }
+
+    fn normalize(&self, (r, c): Cell) -> Option<Cell> {
+        match self.boundary {
+            Boundary::Bounded => {
+                if (0..self.n_rows).contains(&r) && (0..self.n_columns).contains(&c) {
+                    Some((r, c))
+                } else {
+                    None
+                }
+            }
+            Boundary::Toroidal => Some((r.rem_euclid(self.n_rows), c.rem_euclid(self.n_columns))),
+            Boundary::Unbounded => Some((r, c)),
+        }
+    }
+
+    pub fn is_alive(&self, cell: Cell) -> bool {
+        self.normalize(cell)
+            .is_some_and(|cell| self.alive.contains(&cell))
+    }
+
+    pub fn set(&mut self, cell: Cell, alive: bool) {
+        if let Some(cell) = self.normalize(cell) {
+            if self.pinned_on.contains(&cell) || self.pinned_off.contains(&cell) {
+                return;
+            }
+            if alive {
+                self.alive.insert(cell);
+            } else {
+                self.alive.remove(&cell);
+            }
+        }
+    }
+
+    /// Forces the cell to stay in the given state regardless of the rule.
+    pub fn pin(&mut self, cell: Cell, alive: bool) {
+        if let Some(cell) = self.normalize(cell) {
+            if alive {
+                self.pinned_off.remove(&cell);
+                self.pinned_on.insert(cell);
+                self.alive.insert(cell);
+            } else {
+                self.pinned_on.remove(&cell);
+                self.pinned_off.insert(cell);
+                self.alive.remove(&cell);
+            }
+        }
+    }
+
+    pub fn n_alive(&self) -> usize {
+        self.alive.len()
+    }
+
+    pub fn alive(&self) -> impl Iterator<Item = &Cell> {
+        self.alive.iter()
+    }
+
+    pub fn step(&mut self) {
+        let mut neighbours: HashMap<Cell, usize> = HashMap::new();
+        for &(r, c) in &self.alive {
+            for dr in -1..=1 {
+                for dc in -1..=1 {
+                    if (dr, dc) == (0, 0) {
+                        continue;
+                    }
+                    if let Some(n) = self.normalize((r + dr, c + dc)) {
+                        *neighbours.entry(n).or_default() += 1;
+                    }
+                }
+            }
+        }
+        // Dead cells without live neighbours only matter for B0 rules, which are never unbounded
+        let mut candidates = self.alive.clone();
+        candidates.extend(neighbours.keys());
+        if self.rule.birth[0] {
+            candidates
+                .extend((0..self.n_rows).flat_map(|r| (0..self.n_columns).map(move |c| (r, c))));
+        }
+        let mut next = candidates
+            .into_iter()
+            .filter(|cell| {
+                let count = neighbours.get(cell).copied().unwrap_or(0);
+                self.rule.is_alive_next(self.alive.contains(cell), count)
+            })
+            .collect::<HashSet<_>>();
+        next.retain(|cell| !self.pinned_off.contains(cell));
+        next.extend(self.pinned_on.iter());
+        self.alive = next;
+    }
+
+    pub fn run(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    fn snapshot(&self) -> Vec<Cell> {
+        let mut cells = self.alive.iter().copied().collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Steps until a board repeats, giving up after `max_steps`. The automaton is left at the
+    /// first repetition.
+    pub fn find_cycle(&mut self, max_steps: usize) -> Option<Cycle> {
+        let mut seen = HashMap::new();
+        seen.insert(self.snapshot(), 0);
+        for step in 1..=max_steps {
+            self.step();
+            if let Some(start) = seen.insert(self.snapshot(), step) {
+                return Some(Cycle {
+                    start,
+                    period: step - start,
+                });
+            }
+        }
+        None
+    }
+
+    /// Steps until the board stops changing, returning the number of steps taken including the
+    /// one that changed nothing.
+    pub fn run_until_stable(&mut self, max_steps: usize) -> Option<usize> {
+        for step in 1..=max_steps {
+            let before = self.alive.clone();
+            self.step();
+            if before == self.alive {
+                return Some(step);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn life() -> Rule {
+        "B3/S23".parse().unwrap()
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let mut blinker =
+            Automaton::parse(".....\n.###.\n.....", life(), Boundary::Bounded).unwrap();
+        assert_eq!(
+            blinker.find_cycle(10),
+            Some(Cycle {
+                start: 0,
+                period: 2
+            })
+        );
+        assert_eq!(blinker.run_until_stable(10), None);
+    }
+
+    #[test]
+    fn block_is_stable() {
+        let mut block =
+            Automaton::parse("....\n.##.\n.##.\n....", life(), Boundary::Bounded).unwrap();
+        assert_eq!(block.run_until_stable(10), Some(1));
+        assert_eq!(block.n_alive(), 4);
+        assert_eq!(
+            block.find_cycle(10),
+            Some(Cycle {
+                start: 0,
+                period: 1
+            })
+        );
+    }
+
+    #[test]
+    fn glider_wraps_around_torus() {
+        const N: usize = 6;
+        let board = [".#....", "..#...", "###...", "......", "......", "......"].join("\n");
+        let mut glider = Automaton::parse(&board, life(), Boundary::Toroidal).unwrap();
+        let start = glider.snapshot();
+        glider.run(4);
+        let shifted = start
+            .iter()
+            .map(|&(r, c)| ((r + 1) % N as i64, (c + 1) % N as i64))
+            .collect::<HashSet<_>>();
+        assert_eq!(glider.alive().copied().collect::<HashSet<_>>(), shifted);
+        glider.run(4 * N - 4);
+        assert_eq!(glider.snapshot(), start);
+    }
+
+    #[test]
+    fn glider_escapes_unbounded_board() {
+        let mut glider = Automaton::parse(".#.\n..#\n###", life(), Boundary::Unbounded).unwrap();
+        glider.run(40);
+        assert_eq!(glider.n_alive(), 5);
+        assert!(glider.alive().all(|&(r, c)| r >= 10 && c >= 10));
+    }
+
+    #[test]
+    fn parses_rules() {
+        let highlife = "B36/S23".parse::<Rule>().unwrap();
+        assert!(highlife.is_alive_next(false, 6));
+        assert!(!life().is_alive_next(false, 6));
+        assert!(highlife.is_alive_next(true, 2));
+        assert!(!highlife.is_alive_next(true, 6));
+        assert_eq!("b3/s23".parse::<Rule>().unwrap(), life());
+        for invalid in ["B3S23", "B9/S23", "S23/B3", "B3/S2x"] {
+            assert!(invalid.parse::<Rule>().is_err(), "{} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn b0_needs_a_finite_board() {
+        let rule = "B0/S".parse::<Rule>().unwrap();
+        assert!(Automaton::new(rule.clone(), Boundary::Unbounded, 3, 3).is_err());
+        let mut torus = Automaton::new(rule, Boundary::Toroidal, 3, 4).unwrap();
+        torus.step();
+        assert_eq!(torus.n_alive(), 12);
+        torus.step();
+        assert_eq!(torus.n_alive(), 0);
+    }
+
+    #[test]
+    fn pinned_cells_ignore_the_rule() {
+        // The cell above the blinker would be born on the first step, the far corner would die
+        let mut blinker =
+            Automaton::parse(".....\n.###.\n.....", life(), Boundary::Bounded).unwrap();
+        blinker.pin((0, 2), false);
+        blinker.pin((2, 4), true);
+        blinker.set((0, 2), true);
+        for _ in 0..4 {
+            blinker.step();
+            assert!(!blinker.is_alive((0, 2)));
+            assert!(blinker.is_alive((2, 4)));
+        }
+    }
+}
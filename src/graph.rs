@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Directed,
+    Undirected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    Dense,
+    Sparse,
+}
+
+#[derive(Debug, Clone)]
+enum Edges<W> {
+    // Row-major adjacency matrix, `capacity` nodes per row so that adding nodes stays amortized.
+    Dense {
+        data: Vec<Option<W>>,
+        capacity: usize,
+    },
+    Sparse(Vec<Vec<(usize, W)>>),
+}
+
+/// Graph over named nodes, names are interned to indices in insertion order.
+/// Unweighted graphs use `()` as the weight.
+#[derive(Debug, Clone)]
+pub struct Graph<W = ()> {
+    kind: EdgeKind,
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+    edges: Edges<W>,
+}
+
+impl<W: Clone> Graph<W> {
+    pub fn new(kind: EdgeKind, storage: Storage) -> Graph<W> {
+        let edges = match storage {
+            Storage::Dense => Edges::Dense {
+                data: vec![],
+                capacity: 0,
+            },
+            Storage::Sparse => Edges::Sparse(vec![]),
+        };
+        Graph {
+            kind,
+            names: vec![],
+            indices: HashMap::new(),
+            edges,
+        }
+    }
+
+    pub fn from_edges<'a>(
+        kind: EdgeKind,
+        storage: Storage,
+        edges: impl IntoIterator<Item = (&'a str, &'a str, W)>,
+    ) -> Graph<W> {
+        let mut graph = Graph::new(kind, storage);
+        for (from, to, weight) in edges {
+            graph.add_edge(from, to, weight);
+        }
+        graph
+    }
+
+    pub fn add_node(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        let index = self.names.len();
+        self.names.push(name.to_owned());
+        self.indices.insert(name.to_owned(), index);
+        match &mut self.edges {
+            Edges::Dense { data, capacity } => {
+                if index >= *capacity {
+                    let new_capacity = (*capacity * 2).max(4);
+                    let mut new_data = vec![None; new_capacity * new_capacity];
+                    for r in 0..index {
+                        for c in 0..index {
+                            new_data[r * new_capacity + c] = data[r * *capacity + c].take();
+                        }
+                    }
+                    *data = new_data;
+                    *capacity = new_capacity;
+                }
+            }
+            Edges::Sparse(adjacency) => adjacency.push(vec![]),
+        }
+        index
+    }
+
+    /// Adds an edge between two nodes, creating them if needed. An existing edge is reweighted.
+    pub fn add_edge(&mut self, from: &str, to: &str, weight: W) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        if self.kind == EdgeKind::Undirected && from != to {
+            self.set_edge(to, from, weight.clone());
+        }
+        self.set_edge(from, to, weight);
+    }
+
+    fn set_edge(&mut self, from: usize, to: usize, weight: W) {
+        match &mut self.edges {
+            Edges::Dense { data, capacity } => data[from * *capacity + to] = Some(weight),
+            Edges::Sparse(adjacency) => {
+                let row = &mut adjacency[from];
+                if let Some(edge) = row.iter_mut().find(|(n, _)| *n == to) {
+                    edge.1 = weight;
+                } else {
+                    row.push((to, weight));
+                }
+            }
+        }
+    }
+}
+
+impl<W> Graph<W> {
+    pub fn kind(&self) -> EdgeKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn weight(&self, from: usize, to: usize) -> Option<&W> {
+        match &self.edges {
+            Edges::Dense { data, capacity } => data[from * capacity + to].as_ref(),
+            Edges::Sparse(adjacency) => adjacency[from]
+                .iter()
+                .find(|(n, _)| *n == to)
+                .map(|(_, w)| w),
+        }
+    }
+
+    pub fn neighbors(&self, index: usize) -> Box<dyn Iterator<Item = (usize, &W)> + '_> {
+        match &self.edges {
+            Edges::Dense { data, capacity } => Box::new(
+                data[index * capacity..index * capacity + self.len()]
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(n, w)| w.as_ref().map(|w| (n, w))),
+            ),
+            Edges::Sparse(adjacency) => Box::new(adjacency[index].iter().map(|(n, w)| (*n, w))),
+        }
+    }
+
+    /// Graphviz representation, `label` gives the optional edge label for a weight.
+    pub fn to_dot(&self, label: impl Fn(&W) -> Option<String>) -> String {
+        let (keyword, connector) = match self.kind {
+            EdgeKind::Directed => ("digraph", "->"),
+            EdgeKind::Undirected => ("graph", "--"),
+        };
+        let mut result = format!("{} {{\n", keyword);
+        for name in &self.names {
+            result.push_str(&format!("    {:?};\n", name));
+        }
+        for from in 0..self.len() {
+            for (to, weight) in self.neighbors(from) {
+                if self.kind == EdgeKind::Undirected && to < from {
+                    continue;
+                }
+                result.push_str(&format!(
+                    "    {:?} {} {:?}",
+                    self.names[from], connector, self.names[to]
+                ));
+                if let Some(l) = label(weight) {
+                    result.push_str(&format!(" [label={:?}]", l));
+                }
+                result.push_str(";\n");
+            }
+        }
+        result.push_str("}\n");
+        result
+    }
+}
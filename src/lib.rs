@@ -1,3 +1,11 @@
+mod automaton;
+mod graph;
+mod ocr;
+mod random;
 mod resources;
 
+pub use automaton::*;
+pub use graph::*;
+pub use ocr::*;
+pub use random::*;
 pub use resources::*;
@@ -0,0 +1,326 @@
+use std::collections::HashSet;
+use std::fmt;
+
+// Letters seen in puzzle answers so far, drawn 6 and 10 pixels high.
+const SMALL_LETTERS: &[(char, &[&str])] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_LETTERS: &[(char, &[&str])] = &[
+    (
+        'A',
+        &[
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        &[
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        &[
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        &[
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        &[
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        &[
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        &[
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        &[
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+/// Characters drawn as lit pixels in text grids.
+const LIT: [char; 2] = ['#', '█'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// No font has glyphs of this height.
+    UnknownHeight(usize),
+    /// Glyphs matching no letter, as their index and first lit column. They show as `?` in
+    /// `text`.
+    Unrecognized {
+        text: String,
+        positions: Vec<(usize, i64)>,
+    },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::UnknownHeight(height) => {
+                write!(f, "no font has letters {} pixels high", height)
+            }
+            OcrError::Unrecognized { text, positions } => {
+                let positions = positions
+                    .iter()
+                    .map(|(index, column)| format!("glyph {} at column {}", index, column))
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "unrecognized glyphs in '{}': {}",
+                    text,
+                    positions.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Reads block letters from lit `(x, y)` points, wherever they are placed.
+pub fn recognize(points: impl IntoIterator<Item = (i64, i64)>) -> Result<String, OcrError> {
+    let points = points.into_iter().collect::<HashSet<_>>();
+    let (Some(min_x), Some(max_x), Some(min_y), Some(max_y)) = (
+        points.iter().map(|p| p.0).min(),
+        points.iter().map(|p| p.0).max(),
+        points.iter().map(|p| p.1).min(),
+        points.iter().map(|p| p.1).max(),
+    ) else {
+        return Err(OcrError::UnknownHeight(0));
+    };
+    let rows = (min_y..=max_y)
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| if points.contains(&(x, y)) { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    read(&rows, min_x)
+}
+
+/// Reads block letters from text drawn with `#` or `█`, any other character is unlit.
+pub fn recognize_grid(grid: &str) -> Result<String, OcrError> {
+    recognize(grid.lines().enumerate().flat_map(|(y, line)| {
+        line.chars()
+            .enumerate()
+            .filter(|(_, c)| LIT.contains(c))
+            .map(move |(x, _)| (x as i64, y as i64))
+    }))
+}
+
+// Letters sit in cells of a fixed width starting at the leftmost lit column, as some fill their
+// whole cell. Each cell is cropped to its lit columns, `min_x` is the x of the first column.
+fn read(rows: &[String], min_x: i64) -> Result<String, OcrError> {
+    let (letters, pitch) = match rows.len() {
+        6 => (SMALL_LETTERS, 5),
+        10 => (LARGE_LETTERS, 8),
+        height => return Err(OcrError::UnknownHeight(height)),
+    };
+    let width = rows[0].len();
+    let is_lit = |column: &usize| rows.iter().any(|r| r.as_bytes()[*column] == b'#');
+
+    let mut text = String::new();
+    let mut positions = vec![];
+    for cell in (0..width).step_by(pitch) {
+        let mut lit = (cell..(cell + pitch).min(width)).filter(is_lit);
+        let Some(start) = lit.next() else {
+            continue;
+        };
+        let end = lit.next_back().unwrap_or(start) + 1;
+        let glyph = rows.iter().map(|r| &r[start..end]).collect::<Vec<_>>();
+        match letters.iter().find(|(_, g)| *g == glyph.as_slice()) {
+            Some(&(letter, _)) => text.push(letter),
+            None => {
+                positions.push((text.chars().count(), min_x + start as i64));
+                text.push('?');
+            }
+        }
+    }
+    if positions.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::Unrecognized { text, positions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &[&str] = &["####", "####", "####", "####", "####", "####"];
+
+    // Lit points of `text` with one letter every `pitch` columns from `origin`, `?` is a block
+    fn draw(
+        letters: &[(char, &[&str])],
+        text: &str,
+        pitch: i64,
+        origin: (i64, i64),
+    ) -> Vec<(i64, i64)> {
+        let mut points = vec![];
+        for (i, c) in text.chars().enumerate() {
+            let glyph = match c {
+                '?' => BLOCK,
+                c => letters.iter().find(|(l, _)| *l == c).unwrap().1,
+            };
+            for (y, row) in glyph.iter().enumerate() {
+                for (x, _) in row.chars().enumerate().filter(|&(_, p)| p == '#') {
+                    points.push((origin.0 + i as i64 * pitch + x as i64, origin.1 + y as i64));
+                }
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn reads_every_letter() {
+        for (letters, pitch) in [(SMALL_LETTERS, 5), (LARGE_LETTERS, 8)] {
+            for &(letter, _) in letters {
+                let text = letter.to_string();
+                assert_eq!(recognize(draw(letters, &text, pitch, (3, -2))), Ok(text));
+            }
+        }
+    }
+
+    #[test]
+    fn reads_words() {
+        for (letters, pitch, word) in [
+            (SMALL_LETTERS, 5, "PHUBZCEL"),
+            (LARGE_LETTERS, 8, "ZKAXJNGB"),
+        ] {
+            assert_eq!(
+                recognize(draw(letters, word, pitch, (0, 0))),
+                Ok(word.to_owned())
+            );
+        }
+    }
+
+    // Y fills its whole cell, so no unlit column separates it from its neighbours
+    #[test]
+    fn reads_words_with_y() {
+        let grid = "\
+#...#.##..#...#####.
+#...##..#.#...##....
+.#.#.#..#..#.#.###..
+..#..####...#..#....
+..#..#..#...#..#....
+..#..#..#...#..####.";
+        assert_eq!(recognize_grid(grid), Ok("YAYE".to_owned()));
+        let shifted = grid
+            .replace('#', "█")
+            .lines()
+            .map(|l| format!("  {}", l))
+            .collect::<Vec<_>>();
+        assert_eq!(recognize_grid(&shifted.join("\n")), Ok("YAYE".to_owned()));
+    }
+
+    #[test]
+    fn reports_unknown_glyphs_where_they_are() {
+        let points = draw(SMALL_LETTERS, "A?BY?", 5, (-7, 40));
+        assert_eq!(
+            recognize(points),
+            Err(OcrError::Unrecognized {
+                text: "A?BY?".to_owned(),
+                positions: vec![(1, -2), (4, 13)],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_heights() {
+        assert_eq!(recognize_grid("#\n#\n#"), Err(OcrError::UnknownHeight(3)));
+        assert_eq!(recognize_grid(".."), Err(OcrError::UnknownHeight(0)));
+    }
+}
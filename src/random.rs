@@ -0,0 +1,22 @@
+/// Xorshift64 generator, the same sequence for the same seed.
+#[derive(Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// A zero seed would only ever produce zeroes, so it is replaced.
+    pub fn new(seed: u64) -> XorShift {
+        XorShift(if seed == 0 { 0x9e3779b97f4a7c15 } else { seed })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Roughly uniform in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}